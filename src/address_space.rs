@@ -0,0 +1,321 @@
+use crate::core_style::CoreStyle;
+use crate::cpu::CPU_TYPE_ARM64_32;
+use crate::error::MachoError;
+use crate::mach_header::MachHeader;
+use crate::macho::Macho;
+use crate::segment::Segment;
+use crate::vm_prot::VmProt;
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+/// How a `VirtualMemoryCursor` treats addresses which are not
+/// mapped by any segment or whose memory was not captured
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum UnmappedMode {
+    /// Reading such an address fails with the
+    /// `MachoError::UnmappedAddress` or `MachoError::NotCaptured`
+    /// as inner error
+    #[default]
+    Error,
+    /// Such addresses read as zeros
+    ZeroFill,
+}
+
+/// Size of pointers in the memory of the crashed process
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PointerWidth {
+    Bits32,
+    Bits64,
+}
+
+impl PointerWidth {
+    /// Pointer width of processes described by `header`. arm64_32
+    /// uses 64-bit registers but 32-bit pointers.
+    pub fn of(header: &MachHeader) -> Self {
+        if header.is_64() && header.cputype != CPU_TYPE_ARM64_32 {
+            PointerWidth::Bits64
+        } else {
+            PointerWidth::Bits32
+        }
+    }
+
+    /// Size of a pointer in bytes
+    pub fn bytes(self) -> usize {
+        match self {
+            PointerWidth::Bits32 => 4,
+            PointerWidth::Bits64 => 8,
+        }
+    }
+}
+
+/// Memory at an address up to where its source changes
+enum Chunk<'a> {
+    /// Bytes stored in the core dump
+    Stored(&'a [u8]),
+    /// Number of bytes which read as zeros
+    Zeros(usize),
+}
+
+/// Reads from the memory of the crashed process. Addresses are
+/// virtual addresses, reads may span adjacent segments. Memory
+/// which is mapped but not stored in the core dump (past
+/// `filesize`) reads as zeros in full core dumps. Partial core
+/// dumps leave out memory on purpose, see `CoreStyle`, so reads
+/// of it fail with `MachoError::NotCaptured`.
+impl Macho {
+    /// Reads `len` bytes starting at `vaddr`
+    pub fn read(&self, vaddr: u64, len: usize) -> Result<Vec<u8>, MachoError> {
+        let mut buf = vec![0; len];
+        self.read_into(vaddr, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Fills `buf` with the bytes starting at `vaddr`
+    pub fn read_into(&self, vaddr: u64, buf: &mut [u8]) -> Result<(), MachoError> {
+        let mut done = 0;
+        while done < buf.len() {
+            let address = vaddr
+                .checked_add(done as u64)
+                .ok_or(MachoError::UnmappedAddress { address: u64::MAX })?;
+            done += fill(self.chunk(address)?, &mut buf[done..]);
+        }
+        Ok(())
+    }
+
+    /// Reads a single byte
+    pub fn read_u8(&self, vaddr: u64) -> Result<u8, MachoError> {
+        Ok(self.read_array::<1>(vaddr)?[0])
+    }
+
+    /// Reads a `u16` in the byte order of the core dump
+    pub fn read_u16(&self, vaddr: u64) -> Result<u16, MachoError> {
+        let bytes = self.read_array(vaddr)?;
+        Ok(self.header.endian().u16(bytes))
+    }
+
+    /// Reads a `u32` in the byte order of the core dump
+    pub fn read_u32(&self, vaddr: u64) -> Result<u32, MachoError> {
+        let bytes = self.read_array(vaddr)?;
+        Ok(self.header.endian().u32(bytes))
+    }
+
+    /// Reads a `u64` in the byte order of the core dump
+    pub fn read_u64(&self, vaddr: u64) -> Result<u64, MachoError> {
+        let bytes = self.read_array(vaddr)?;
+        Ok(self.header.endian().u64(bytes))
+    }
+
+    /// Reads a pointer sized according to `pointer_width`
+    pub fn read_ptr(&self, vaddr: u64) -> Result<u64, MachoError> {
+        match self.pointer_width {
+            PointerWidth::Bits64 => self.read_u64(vaddr),
+            PointerWidth::Bits32 => self.read_u32(vaddr).map(u64::from),
+        }
+    }
+
+    /// Reads a NUL terminated string. Invalid UTF-8 is replaced.
+    pub fn read_cstring(&self, vaddr: u64) -> Result<String, MachoError> {
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut address = vaddr;
+        // Zeros past the stored content end the string
+        while let Chunk::Stored(rest) = self.chunk(address)? {
+            if let Some(len) = rest.iter().position(|&b| b == 0) {
+                bytes.extend_from_slice(&rest[..len]);
+                break;
+            }
+            bytes.extend_from_slice(rest);
+            address = address
+                .checked_add(rest.len() as u64)
+                .ok_or(MachoError::UnmappedAddress { address: u64::MAX })?;
+        }
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Reads a NUL terminated UTF-16 string in the byte order of
+    /// the core dump. Invalid code units are replaced.
+    pub fn read_utf16(&self, vaddr: u64) -> Result<String, MachoError> {
+        let mut units: Vec<u16> = Vec::new();
+        let mut address = vaddr;
+        loop {
+            let unit = self.read_u16(address)?;
+            if unit == 0 {
+                break;
+            }
+            units.push(unit);
+            address = address
+                .checked_add(2)
+                .ok_or(MachoError::UnmappedAddress { address: u64::MAX })?;
+        }
+        Ok(String::from_utf16_lossy(&units))
+    }
+
+    fn read_array<const N: usize>(&self, vaddr: u64) -> Result<[u8; N], MachoError> {
+        let mut bytes = [0; N];
+        self.read_into(vaddr, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Cursor reading the virtual memory like a file, failing
+    /// on unmapped addresses
+    pub fn cursor(&self) -> VirtualMemoryCursor<'_> {
+        self.cursor_with(UnmappedMode::Error)
+    }
+
+    /// Cursor reading the virtual memory like a file, handling
+    /// unmapped addresses according to `mode`
+    pub fn cursor_with(&self, mode: UnmappedMode) -> VirtualMemoryCursor<'_> {
+        VirtualMemoryCursor {
+            macho: self,
+            pos: 0,
+            mode,
+        }
+    }
+
+    /// All segments whose protection at the time of the dump
+    /// includes every bit of `prot`, sorted by address. For example
+    /// `VmProt::WRITE | VmProt::EXECUTE` finds writable and
+    /// executable memory.
+    pub fn segments_with(&self, prot: VmProt) -> impl Iterator<Item = &Segment> {
+        self.segments_overlapping(0..u64::MAX)
+            .filter(move |segment| segment.initprot.contains(prot))
+    }
+
+    /// Segment containing `vaddr`
+    pub fn segment_containing(&self, vaddr: u64) -> Option<&Segment> {
+        self.index.find(vaddr).map(|i| &self.segments[i])
+    }
+
+    /// Lowest mapped address at or after `vaddr`
+    pub fn next_mapped(&self, vaddr: u64) -> Option<u64> {
+        self.index.next_mapped(vaddr)
+    }
+
+    /// All segments overlapping `range`, sorted by address
+    pub fn segments_overlapping(&self, range: Range<u64>) -> impl Iterator<Item = &Segment> {
+        self.index.overlapping(range).map(move |i| &self.segments[i])
+    }
+
+    /// Segment containing `vaddr` and the offset of `vaddr` in it
+    fn segment_at(&self, vaddr: u64) -> Result<(&Segment, usize), MachoError> {
+        self.segment_containing(vaddr)
+            .map(|segment| (segment, (vaddr - segment.vmaddr as u64) as usize))
+            .ok_or(MachoError::UnmappedAddress { address: vaddr })
+    }
+
+    /// Memory at `vaddr` up to the end of the stored content or
+    /// the end of its segment
+    fn chunk(&self, vaddr: u64) -> Result<Chunk<'_>, MachoError> {
+        let (segment, start) = self.segment_at(vaddr)?;
+        let content = segment.content()?;
+        let end = content.len().min(segment.vmsize);
+        if start < end {
+            Ok(Chunk::Stored(&content[start..end]))
+        } else if self.style == CoreStyle::Full {
+            Ok(Chunk::Zeros(segment.vmsize - start))
+        } else {
+            Err(MachoError::NotCaptured { address: vaddr })
+        }
+    }
+}
+
+/// Copies as much of `chunk` into `buf` as fits and returns the
+/// number of bytes written
+fn fill(chunk: Chunk<'_>, buf: &mut [u8]) -> usize {
+    match chunk {
+        Chunk::Stored(bytes) => {
+            let len = bytes.len().min(buf.len());
+            buf[..len].copy_from_slice(&bytes[..len]);
+            len
+        }
+        Chunk::Zeros(len) => {
+            let len = len.min(buf.len());
+            buf[..len].fill(0);
+            len
+        }
+    }
+}
+
+/// `Read + Seek` view of the virtual memory of a core dump. The
+/// position is a virtual address, reads continue across segment
+/// boundaries. The end of the stream is the end of the highest
+/// mapped segment.
+#[derive(Debug, Clone)]
+pub struct VirtualMemoryCursor<'a> {
+    macho: &'a Macho,
+    pos: u64,
+    mode: UnmappedMode,
+}
+
+impl<'a> VirtualMemoryCursor<'a> {
+    /// Current virtual address
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+}
+
+impl<'a> Read for VirtualMemoryCursor<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let end = self.macho.index.end();
+        let mut done = 0;
+        while done < buf.len() {
+            let address = self.pos.saturating_add(done as u64);
+            if address >= end {
+                break;
+            }
+            let remaining = buf.len() - done;
+            let (hole, err) = match self.macho.chunk(address) {
+                Ok(chunk) => {
+                    done += fill(chunk, &mut buf[done..]);
+                    continue;
+                }
+                // Zero-fill up to the next segment
+                Err(err @ MachoError::UnmappedAddress { .. }) => {
+                    let next = self.macho.next_mapped(address).unwrap_or(end);
+                    (next - address, err)
+                }
+                // Zero-fill up to the end of the segment
+                Err(err @ MachoError::NotCaptured { .. }) => {
+                    let segment = self.macho.segment_containing(address);
+                    let next = segment.map_or(end, |segment| {
+                        (segment.vmaddr as u64).saturating_add(segment.vmsize as u64)
+                    });
+                    (next - address, err)
+                }
+                Err(err) => return Err(io::Error::other(err)),
+            };
+            if self.mode == UnmappedMode::Error {
+                // Return what was read before the hole
+                if done > 0 {
+                    break;
+                }
+                return Err(io::Error::other(err));
+            }
+            let len = usize::try_from(hole).map_or(remaining, |hole| hole.min(remaining));
+            buf[done..done + len].fill(0);
+            done += len;
+        }
+        self.pos = self.pos.saturating_add(done as u64);
+        Ok(done)
+    }
+}
+
+impl<'a> Seek for VirtualMemoryCursor<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(address) => {
+                self.pos = address;
+                return Ok(address);
+            }
+            SeekFrom::End(offset) => (self.macho.index.end(), offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        self.pos = base.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek to an invalid virtual address",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
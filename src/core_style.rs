@@ -0,0 +1,73 @@
+use crate::segment::Segment;
+use crate::thread::{RegisterContext, Thread};
+use crate::vm_prot::VmProt;
+use std::fmt;
+
+/// Which memory a core dump captured. `lldb`'s `process save-core`
+/// can leave out memory, in which case segments store fewer bytes
+/// (`filesize`) than they map (`vmsize`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum CoreStyle {
+    /// All readable memory is stored
+    #[default]
+    Full,
+    /// Only memory which was modified is stored, also known as
+    /// dirty-only
+    ModifiedMemory,
+    /// Only the stacks of the threads are stored
+    StackOnly,
+}
+
+impl CoreStyle {
+    /// Works out the style from which readable segments are stored
+    /// and where the stack pointers of `threads` point:
+    ///
+    /// - `Full` if every readable segment stores at least one byte.
+    ///   Full core dumps may store a segment only partly and leave
+    ///   its tail to be read as zeros.
+    /// - `StackOnly` if some readable segment stores nothing and
+    ///   every segment which stores memory holds a stack pointer.
+    /// - `ModifiedMemory` otherwise.
+    ///
+    /// A partial core dump which shortens segments without leaving
+    /// any out completely is indistinguishable from a full one and
+    /// is detected as `Full`, so its uncaptured tails read as zeros.
+    /// Callers who know better can set `Macho::style`.
+    pub(crate) fn detect(segments: &[Segment], threads: &[Thread]) -> Self {
+        let readable: Vec<&Segment> = segments
+            .iter()
+            .filter(|segment| segment.vmsize > 0 && segment.initprot.contains(VmProt::READ))
+            .collect();
+        if readable.iter().all(|segment| segment.filesize > 0) {
+            return CoreStyle::Full;
+        }
+
+        let is_stack = |segment: &Segment| {
+            threads.iter().filter_map(|thread| thread.context).any(|context| {
+                let sp = context.sp();
+                let start = segment.vmaddr as u64;
+                sp >= start && sp - start < segment.vmsize as u64
+            })
+        };
+        let stored: Vec<&Segment> = readable
+            .into_iter()
+            .filter(|segment| segment.filesize > 0)
+            .collect();
+        if !stored.is_empty() && stored.iter().all(|segment| is_stack(segment)) {
+            CoreStyle::StackOnly
+        } else {
+            CoreStyle::ModifiedMemory
+        }
+    }
+}
+
+impl fmt::Display for CoreStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let style = match self {
+            CoreStyle::Full => "full",
+            CoreStyle::ModifiedMemory => "modified-memory",
+            CoreStyle::StackOnly => "stack",
+        };
+        write!(f, "{}", style)
+    }
+}
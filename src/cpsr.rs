@@ -0,0 +1,158 @@
+use std::fmt;
+
+/// Current Program Status Register of ARM64 threads, the saved
+/// PSTATE at the time of the dump
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Cpsr(pub u32);
+
+impl Cpsr {
+    fn bit(self, n: u32) -> bool {
+        self.0 & (1 << n) != 0
+    }
+
+    /// Negative condition flag
+    pub fn n(self) -> bool {
+        self.bit(31)
+    }
+
+    /// Zero condition flag
+    pub fn z(self) -> bool {
+        self.bit(30)
+    }
+
+    /// Carry condition flag
+    pub fn c(self) -> bool {
+        self.bit(29)
+    }
+
+    /// Overflow condition flag
+    pub fn v(self) -> bool {
+        self.bit(28)
+    }
+
+    /// Returns true if the thread ran in AArch32 state (nRW)
+    pub fn is_aarch32(self) -> bool {
+        self.bit(4)
+    }
+
+    /// Exception level the thread ran at. Only defined in
+    /// AArch64 state.
+    pub fn exception_level(self) -> Option<u8> {
+        if self.is_aarch32() {
+            None
+        } else {
+            Some(((self.0 >> 2) & 0b11) as u8)
+        }
+    }
+
+    /// Returns true if the stack pointer of the exception level
+    /// is used (`ELxh`) instead of `SP_EL0` (`ELxt`). Only
+    /// defined in AArch64 state.
+    pub fn sp_elx(self) -> Option<bool> {
+        if self.is_aarch32() {
+            None
+        } else {
+            Some(self.bit(0))
+        }
+    }
+
+    /// Mode field M[4:0] of AArch32 state, like `0x10` for user
+    /// mode
+    pub fn aarch32_mode(self) -> Option<u8> {
+        if self.is_aarch32() {
+            Some((self.0 & 0x1f) as u8)
+        } else {
+            None
+        }
+    }
+
+    /// Debug exceptions are masked
+    pub fn d(self) -> bool {
+        self.bit(9)
+    }
+
+    /// SError interrupts are masked
+    pub fn a(self) -> bool {
+        self.bit(8)
+    }
+
+    /// IRQ interrupts are masked
+    pub fn i(self) -> bool {
+        self.bit(7)
+    }
+
+    /// FIQ interrupts are masked
+    pub fn f(self) -> bool {
+        self.bit(6)
+    }
+
+    /// Speculative Store Bypass Safe
+    pub fn ssbs(self) -> bool {
+        self.bit(12)
+    }
+
+    /// Privileged Access Never
+    pub fn pan(self) -> bool {
+        self.bit(22)
+    }
+
+    /// User Access Override
+    pub fn uao(self) -> bool {
+        self.bit(23)
+    }
+
+    /// Tag Check Override
+    pub fn tco(self) -> bool {
+        self.bit(25)
+    }
+
+    /// Branch type of the last indirect branch, checked by BTI
+    pub fn btype(self) -> u8 {
+        ((self.0 >> 10) & 0b11) as u8
+    }
+}
+
+/// Formats the register like `nZCv EL0t daif SSBS`. Flags and
+/// mask bits are uppercase when set.
+impl fmt::Display for Cpsr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |set: bool, c: char| if set { c.to_ascii_uppercase() } else { c };
+        write!(
+            f,
+            "{}{}{}{}",
+            flag(self.n(), 'n'),
+            flag(self.z(), 'z'),
+            flag(self.c(), 'c'),
+            flag(self.v(), 'v')
+        )?;
+        match (self.exception_level(), self.sp_elx(), self.aarch32_mode()) {
+            (Some(el), Some(sp_elx), _) => {
+                write!(f, " EL{}{}", el, if sp_elx { 'h' } else { 't' })?
+            }
+            (_, _, Some(mode)) => write!(f, " AArch32 mode 0x{:02x}", mode)?,
+            _ => {}
+        }
+        write!(
+            f,
+            " {}{}{}{}",
+            flag(self.d(), 'd'),
+            flag(self.a(), 'a'),
+            flag(self.i(), 'i'),
+            flag(self.f(), 'f')
+        )?;
+        for (set, name) in [
+            (self.ssbs(), "SSBS"),
+            (self.pan(), "PAN"),
+            (self.uao(), "UAO"),
+            (self.tco(), "TCO"),
+        ] {
+            if set {
+                write!(f, " {}", name)?;
+            }
+        }
+        if self.btype() != 0 {
+            write!(f, " BTYPE={}", self.btype())?;
+        }
+        Ok(())
+    }
+}
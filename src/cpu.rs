@@ -4,25 +4,29 @@ use std::fmt;
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct CpuType(pub u32);
 
+pub const MH_MAGIC: u32 = 0xfeedface;
+pub const MH_CIGAM: u32 = 0xcefaedfe;
 pub const MH_MAGIC_64: u32 = 0xfeedfacf;
 pub const MH_CIGAM_64: u32 = 0xcffaedfe;
 
 const CPU_ARCH_ABI64: u32 = 0x01000000; // 64 bit ABI
+const CPU_ARCH_ABI64_32: u32 = 0x02000000; // 64 bit registers, 32 bit pointers
 
-const CPU_TYPE_ANY: CpuType = CpuType(u32::MAX);
-const CPU_TYPE_VAX: CpuType = CpuType(1);
-const CPU_TYPE_MC680X0: CpuType = CpuType(6);
-const CPU_TYPE_X86: CpuType = CpuType(7);
-const CPU_TYPE_X86_64: CpuType = CpuType(CPU_TYPE_X86.0 | CPU_ARCH_ABI64);
-const CPU_TYPE_MC98000: CpuType = CpuType(10);
-const CPU_TYPE_HPPA: CpuType = CpuType(11);
-const CPU_TYPE_ARM: CpuType = CpuType(12);
-const CPU_TYPE_ARM64: CpuType = CpuType(CPU_TYPE_ARM.0 | CPU_ARCH_ABI64);
-const CPU_TYPE_MC88000: CpuType = CpuType(13);
-const CPU_TYPE_SPARC: CpuType = CpuType(14);
-const CPU_TYPE_I860: CpuType = CpuType(15);
-const CPU_TYPE_POWERPC: CpuType = CpuType(18);
-const CPU_TYPE_POWERPC64: CpuType = CpuType(CPU_TYPE_POWERPC.0 | CPU_ARCH_ABI64);
+pub const CPU_TYPE_ANY: CpuType = CpuType(u32::MAX);
+pub const CPU_TYPE_VAX: CpuType = CpuType(1);
+pub const CPU_TYPE_MC680X0: CpuType = CpuType(6);
+pub const CPU_TYPE_X86: CpuType = CpuType(7);
+pub const CPU_TYPE_X86_64: CpuType = CpuType(CPU_TYPE_X86.0 | CPU_ARCH_ABI64);
+pub const CPU_TYPE_MC98000: CpuType = CpuType(10);
+pub const CPU_TYPE_HPPA: CpuType = CpuType(11);
+pub const CPU_TYPE_ARM: CpuType = CpuType(12);
+pub const CPU_TYPE_ARM64: CpuType = CpuType(CPU_TYPE_ARM.0 | CPU_ARCH_ABI64);
+pub const CPU_TYPE_ARM64_32: CpuType = CpuType(CPU_TYPE_ARM.0 | CPU_ARCH_ABI64_32);
+pub const CPU_TYPE_MC88000: CpuType = CpuType(13);
+pub const CPU_TYPE_SPARC: CpuType = CpuType(14);
+pub const CPU_TYPE_I860: CpuType = CpuType(15);
+pub const CPU_TYPE_POWERPC: CpuType = CpuType(18);
+pub const CPU_TYPE_POWERPC64: CpuType = CpuType(CPU_TYPE_POWERPC.0 | CPU_ARCH_ABI64);
 
 impl fmt::Display for CpuType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -36,6 +40,7 @@ impl fmt::Display for CpuType {
             CPU_TYPE_HPPA => "HPPA",
             CPU_TYPE_ARM => "ARM",
             CPU_TYPE_ARM64 => "ARM64",
+            CPU_TYPE_ARM64_32 => "ARM64_32",
             CPU_TYPE_MC88000 => "MC88000",
             CPU_TYPE_SPARC => "SPARC",
             CPU_TYPE_I860 => "I860",
@@ -51,12 +56,23 @@ impl fmt::Display for CpuType {
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct CpuSubType(pub u32);
 
-const CPU_SUBTYPE_ARM64_ALL: CpuSubType = CpuSubType(0);
-const CPU_SUBTYPE_ARM64_V8: CpuSubType = CpuSubType(1);
-const CPU_SUBTYPE_ARM64E: CpuSubType = CpuSubType(2);
-const CPU_SUBTYPE_X86_ALL: CpuSubType = CpuSubType(3);
-const CPU_SUBTYPE_X86_ARCH1: CpuSubType = CpuSubType(4);
-const CPU_SUBTYPE_X86_64_H: CpuSubType = CpuSubType(8);
+/// Capability bits of a subtype, like the pointer
+/// authentication ABI version of arm64e
+pub const CPU_SUBTYPE_MASK: u32 = 0xff000000;
+
+impl CpuSubType {
+    /// Subtype without capability bits
+    pub fn base(self) -> CpuSubType {
+        CpuSubType(self.0 & !CPU_SUBTYPE_MASK)
+    }
+}
+
+pub const CPU_SUBTYPE_ARM64_ALL: CpuSubType = CpuSubType(0);
+pub const CPU_SUBTYPE_ARM64_V8: CpuSubType = CpuSubType(1);
+pub const CPU_SUBTYPE_ARM64E: CpuSubType = CpuSubType(2);
+pub const CPU_SUBTYPE_X86_ALL: CpuSubType = CpuSubType(3);
+pub const CPU_SUBTYPE_X86_ARCH1: CpuSubType = CpuSubType(4);
+pub const CPU_SUBTYPE_X86_64_H: CpuSubType = CpuSubType(8);
 
 impl fmt::Display for CpuSubType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -0,0 +1,107 @@
+use std::convert::TryInto;
+use std::fmt;
+
+/// Byte order of all fields in a Mach-O, selected by its magic
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    pub fn u16(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            Endian::Little => u16::from_le_bytes(bytes),
+            Endian::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    pub fn u32(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            Endian::Little => u32::from_le_bytes(bytes),
+            Endian::Big => u32::from_be_bytes(bytes),
+        }
+    }
+
+    pub fn u64(self, bytes: [u8; 8]) -> u64 {
+        match self {
+            Endian::Little => u64::from_le_bytes(bytes),
+            Endian::Big => u64::from_be_bytes(bytes),
+        }
+    }
+
+    pub fn u128(self, bytes: [u8; 16]) -> u128 {
+        match self {
+            Endian::Little => u128::from_le_bytes(bytes),
+            Endian::Big => u128::from_be_bytes(bytes),
+        }
+    }
+}
+
+impl fmt::Display for Endian {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endian::Little => write!(f, "Little Endian"),
+            Endian::Big => write!(f, "Big Endian"),
+        }
+    }
+}
+
+/// Reads consecutive fields from a buffer in a given byte order.
+/// Panics when reading past the end of the buffer, so callers
+/// have to check the size of the buffer up front.
+#[derive(Debug, Clone)]
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    endian: Endian,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(buf: &'a [u8], endian: Endian) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            endian,
+        }
+    }
+
+    pub(crate) fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let bytes = self.buf[self.pos..self.pos + N].try_into().unwrap();
+        self.pos += N;
+        bytes
+    }
+
+    pub(crate) fn u8(&mut self) -> u8 {
+        self.bytes::<1>()[0]
+    }
+
+    pub(crate) fn u16(&mut self) -> u16 {
+        let bytes = self.bytes();
+        self.endian.u16(bytes)
+    }
+
+    pub(crate) fn u32(&mut self) -> u32 {
+        let bytes = self.bytes();
+        self.endian.u32(bytes)
+    }
+
+    pub(crate) fn i32(&mut self) -> i32 {
+        self.u32() as i32
+    }
+
+    pub(crate) fn u64(&mut self) -> u64 {
+        let bytes = self.bytes();
+        self.endian.u64(bytes)
+    }
+
+    pub(crate) fn u128(&mut self) -> u128 {
+        let bytes = self.bytes();
+        self.endian.u128(bytes)
+    }
+
+    /// Skips `len` bytes of padding or reserved fields
+    pub(crate) fn skip(&mut self, len: usize) {
+        self.pos += len;
+    }
+}
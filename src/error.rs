@@ -0,0 +1,184 @@
+use crate::validate::Diagnostic;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Errors which can occur while loading a core dump. Every
+/// variant carries the byte offset into the file where the
+/// problem was detected, apart from errors about virtual
+/// addresses which are not tied to the file.
+#[derive(Debug)]
+pub enum MachoError {
+    /// Reading from the underlying file failed
+    Io { offset: usize, source: io::Error },
+    /// File is too small to contain a Mach-O header
+    TruncatedHeader { offset: usize, size: usize },
+    /// Magic does not identify a supported Mach-O
+    BadMagic { offset: usize, magic: u32 },
+    /// Load command does not fit into the file
+    LoadCommandOutOfBounds { offset: usize, cmdsize: u32 },
+    /// File range of a segment lies past the end of the file
+    SegmentOutOfBounds {
+        offset: usize,
+        fileoff: u64,
+        filesize: u64,
+    },
+    /// Payload of a note lies past the end of the file
+    NoteOutOfBounds {
+        offset: usize,
+        noteoff: u64,
+        size: u64,
+    },
+    /// Payload of a note does not match the layout of its owner.
+    /// `offset` is the offset of the payload.
+    MalformedNote { offset: usize, owner: String },
+    /// Architecture slice of a universal binary lies past the
+    /// end of the file. `offset` is the offset of its `fat_arch`.
+    SliceOutOfBounds {
+        offset: usize,
+        slice_offset: u64,
+        size: u64,
+    },
+    /// Virtual address is not covered by any segment
+    UnmappedAddress { address: u64 },
+    /// Virtual address is mapped, but its memory was left out
+    /// of a partial core dump
+    NotCaptured { address: u64 },
+    /// Thread state flavor is not known for the CPU type.
+    /// `offset` is the offset of the flavor.
+    UnsupportedThreadFlavor { offset: usize, flavor: u32 },
+    /// State of a thread flavor does not fit into its command.
+    /// `offset` is the offset of the flavor.
+    ThreadStateOutOfBounds {
+        offset: usize,
+        flavor: u32,
+        count: u32,
+    },
+    /// Strict validation found errors. `offset` is the offset
+    /// of the first error.
+    Validation {
+        offset: usize,
+        diagnostics: Vec<Diagnostic>,
+    },
+}
+
+impl MachoError {
+    /// Byte offset into the file where the error was detected.
+    /// Returns 0 for errors about virtual addresses.
+    pub fn offset(&self) -> usize {
+        match *self {
+            MachoError::Io { offset, .. }
+            | MachoError::TruncatedHeader { offset, .. }
+            | MachoError::BadMagic { offset, .. }
+            | MachoError::LoadCommandOutOfBounds { offset, .. }
+            | MachoError::SegmentOutOfBounds { offset, .. }
+            | MachoError::NoteOutOfBounds { offset, .. }
+            | MachoError::MalformedNote { offset, .. }
+            | MachoError::SliceOutOfBounds { offset, .. }
+            | MachoError::UnsupportedThreadFlavor { offset, .. }
+            | MachoError::ThreadStateOutOfBounds { offset, .. }
+            | MachoError::Validation { offset, .. } => offset,
+            MachoError::UnmappedAddress { .. } | MachoError::NotCaptured { .. } => 0,
+        }
+    }
+}
+
+impl fmt::Display for MachoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MachoError::Io { offset, source } => {
+                write!(f, "I/O error at offset 0x{:x}: {}", offset, source)
+            }
+            MachoError::TruncatedHeader { offset, size } => write!(
+                f,
+                "truncated header at offset 0x{:x}: only {} bytes available",
+                offset, size
+            ),
+            MachoError::BadMagic { offset, magic } => {
+                write!(f, "bad magic 0x{:08x} at offset 0x{:x}", magic, offset)
+            }
+            MachoError::LoadCommandOutOfBounds { offset, cmdsize } => write!(
+                f,
+                "load command at offset 0x{:x} with size {} is out of bounds",
+                offset, cmdsize
+            ),
+            MachoError::SegmentOutOfBounds {
+                offset,
+                fileoff,
+                filesize,
+            } => write!(
+                f,
+                "segment command at offset 0x{:x} references file range 0x{:x}..0x{:x} past end of file",
+                offset,
+                fileoff,
+                fileoff.saturating_add(*filesize)
+            ),
+            MachoError::NoteOutOfBounds {
+                offset,
+                noteoff,
+                size,
+            } => write!(
+                f,
+                "note command at offset 0x{:x} references file range 0x{:x}..0x{:x} past end of file",
+                offset,
+                noteoff,
+                noteoff.saturating_add(*size)
+            ),
+            MachoError::MalformedNote { offset, owner } => {
+                write!(f, "malformed \"{}\" note at offset 0x{:x}", owner, offset)
+            }
+            MachoError::SliceOutOfBounds {
+                offset,
+                slice_offset,
+                size,
+            } => write!(
+                f,
+                "architecture at offset 0x{:x} references slice 0x{:x}..0x{:x} past end of file",
+                offset,
+                slice_offset,
+                slice_offset.saturating_add(*size)
+            ),
+            MachoError::UnmappedAddress { address } => {
+                write!(f, "address 0x{:x} is not mapped", address)
+            }
+            MachoError::NotCaptured { address } => write!(
+                f,
+                "address 0x{:x} is mapped but not captured in the core dump",
+                address
+            ),
+            MachoError::UnsupportedThreadFlavor { offset, flavor } => write!(
+                f,
+                "unsupported thread flavor {} at offset 0x{:x}",
+                flavor, offset
+            ),
+            MachoError::ThreadStateOutOfBounds {
+                offset,
+                flavor,
+                count,
+            } => write!(
+                f,
+                "thread flavor {} at offset 0x{:x} with {} words does not fit into its command",
+                flavor, offset, count
+            ),
+            MachoError::Validation {
+                offset,
+                diagnostics,
+            } => {
+                write!(f, "validation failed at offset 0x{:x}", offset)?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n\t{}", diagnostic)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for MachoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MachoError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use std::fmt;
+
+/// First `brk` immediate used by clang for pointer authentication
+/// failures, followed by one value per key (IA, IB, DA, DB)
+const BRK_PTRAUTH_FAILURE: u16 = 0xc470;
+
+/// Exception Syndrome Register of ARM64, describing why the last
+/// exception was taken
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Esr(pub u32);
+
+impl Esr {
+    /// Exception Class, bits 31:26
+    pub fn class(self) -> ExceptionClass {
+        ExceptionClass::from((self.0 >> 26) as u8)
+    }
+
+    /// Returns true if the trapped instruction was 32 bits wide
+    pub fn il(self) -> bool {
+        self.0 & (1 << 25) != 0
+    }
+
+    /// Instruction Specific Syndrome, bits 24:0
+    pub fn iss(self) -> u32 {
+        self.0 & 0x01ff_ffff
+    }
+
+    /// Fault status of data and instruction aborts
+    pub fn fault_status(self) -> Option<FaultStatus> {
+        match self.class() {
+            ExceptionClass::DataAbort { .. } | ExceptionClass::InstructionAbort { .. } => {
+                Some(FaultStatus::from((self.iss() & 0x3f) as u8))
+            }
+            _ => None,
+        }
+    }
+
+    /// For data aborts, returns true if the access was a write
+    /// (WnR bit). Cache maintenance counts as write.
+    pub fn is_write(self) -> Option<bool> {
+        match self.class() {
+            ExceptionClass::DataAbort { .. } => Some(self.iss() & (1 << 6) != 0),
+            _ => None,
+        }
+    }
+
+    /// For aborts, returns true if the fault address register
+    /// holds the faulting address (FnV bit clear)
+    pub fn far_valid(self) -> Option<bool> {
+        match self.class() {
+            ExceptionClass::DataAbort { .. } | ExceptionClass::InstructionAbort { .. } => {
+                Some(self.iss() & (1 << 10) == 0)
+            }
+            _ => None,
+        }
+    }
+
+    /// Immediate of the `brk` instruction which caused the
+    /// exception
+    pub fn brk_immediate(self) -> Option<u16> {
+        match self.class() {
+            ExceptionClass::Brk => Some(self.iss() as u16),
+            _ => None,
+        }
+    }
+
+    /// Key of a failed pointer authentication, either from a
+    /// FPAC exception or from the `brk` emitted by the compiler
+    /// when FPAC is not available
+    pub fn pac_key(self) -> Option<PacKey> {
+        match self.class() {
+            ExceptionClass::PacFailure => Some(PacKey::from_bits(self.iss() & 0b11)),
+            ExceptionClass::Brk => {
+                let key = self.iss() as u16;
+                if (BRK_PTRAUTH_FAILURE..BRK_PTRAUTH_FAILURE + 4).contains(&key) {
+                    Some(PacKey::from_bits((key - BRK_PTRAUTH_FAILURE) as u32))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Describes the exception, like
+/// `data abort: translation fault (level 3) on write`
+impl fmt::Display for Esr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.class())?;
+        if let Some(status) = self.fault_status() {
+            write!(f, ": {}", status)?;
+        }
+        match self.is_write() {
+            Some(true) => write!(f, " on write")?,
+            Some(false) => write!(f, " on read")?,
+            None => {}
+        }
+        if let Some(immediate) = self.brk_immediate() {
+            write!(f, " #0x{:x}", immediate)?;
+        }
+        match (self.class(), self.pac_key()) {
+            (ExceptionClass::Brk, Some(key)) => {
+                write!(f, " (pointer authentication failure with key {})", key)
+            }
+            (_, Some(key)) => write!(f, " with key {}", key),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Exception Class of the ESR. Classes which can be taken from
+/// a lower exception level record where they came from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExceptionClass {
+    /// Reason is unknown, like undefined instructions
+    Unknown,
+    /// Trapped `wfi` or `wfe`
+    WfiWfe,
+    /// Trapped access to SIMD or floating point registers
+    SimdFpAccess,
+    /// Trapped use of a pointer authentication instruction
+    PacTrap,
+    /// Illegal execution state
+    IllegalState,
+    /// `svc` instruction in AArch64 state
+    Svc,
+    /// Trapped `msr`, `mrs` or system instruction
+    SysReg,
+    /// Trapped access to SVE registers
+    SveAccess,
+    /// Pointer authentication failure (FPAC)
+    PacFailure,
+    /// Instruction abort, like executing unmapped memory
+    InstructionAbort { lower_el: bool },
+    /// Misaligned program counter
+    PcAlignment,
+    /// Data abort, like accessing unmapped memory
+    DataAbort { lower_el: bool },
+    /// Misaligned stack pointer
+    SpAlignment,
+    /// Trapped floating point exception
+    FpException,
+    /// Asynchronous error
+    SError,
+    /// Hardware breakpoint
+    Breakpoint { lower_el: bool },
+    /// Single step
+    SoftwareStep { lower_el: bool },
+    /// Hardware watchpoint
+    Watchpoint { lower_el: bool },
+    /// `brk` instruction in AArch64 state
+    Brk,
+    /// Class which is not known
+    Other(u8),
+}
+
+impl From<u8> for ExceptionClass {
+    fn from(ec: u8) -> Self {
+        match ec {
+            0x00 => ExceptionClass::Unknown,
+            0x01 => ExceptionClass::WfiWfe,
+            0x07 => ExceptionClass::SimdFpAccess,
+            0x09 => ExceptionClass::PacTrap,
+            0x0e => ExceptionClass::IllegalState,
+            0x15 => ExceptionClass::Svc,
+            0x18 => ExceptionClass::SysReg,
+            0x19 => ExceptionClass::SveAccess,
+            0x1c => ExceptionClass::PacFailure,
+            0x20 | 0x21 => ExceptionClass::InstructionAbort {
+                lower_el: ec == 0x20,
+            },
+            0x22 => ExceptionClass::PcAlignment,
+            0x24 | 0x25 => ExceptionClass::DataAbort {
+                lower_el: ec == 0x24,
+            },
+            0x26 => ExceptionClass::SpAlignment,
+            0x2c => ExceptionClass::FpException,
+            0x2f => ExceptionClass::SError,
+            0x30 | 0x31 => ExceptionClass::Breakpoint {
+                lower_el: ec == 0x30,
+            },
+            0x32 | 0x33 => ExceptionClass::SoftwareStep {
+                lower_el: ec == 0x32,
+            },
+            0x34 | 0x35 => ExceptionClass::Watchpoint {
+                lower_el: ec == 0x34,
+            },
+            0x3c => ExceptionClass::Brk,
+            _ => ExceptionClass::Other(ec),
+        }
+    }
+}
+
+impl fmt::Display for ExceptionClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let class = match self {
+            ExceptionClass::Unknown => "unknown exception",
+            ExceptionClass::WfiWfe => "trapped wfi/wfe",
+            ExceptionClass::SimdFpAccess => "trapped SIMD/FP access",
+            ExceptionClass::PacTrap => "trapped pointer authentication instruction",
+            ExceptionClass::IllegalState => "illegal execution state",
+            ExceptionClass::Svc => "supervisor call",
+            ExceptionClass::SysReg => "trapped system register access",
+            ExceptionClass::SveAccess => "trapped SVE access",
+            ExceptionClass::PacFailure => "pointer authentication failure",
+            ExceptionClass::InstructionAbort { .. } => "instruction abort",
+            ExceptionClass::PcAlignment => "PC alignment fault",
+            ExceptionClass::DataAbort { .. } => "data abort",
+            ExceptionClass::SpAlignment => "SP alignment fault",
+            ExceptionClass::FpException => "floating point exception",
+            ExceptionClass::SError => "SError interrupt",
+            ExceptionClass::Breakpoint { .. } => "hardware breakpoint",
+            ExceptionClass::SoftwareStep { .. } => "software step",
+            ExceptionClass::Watchpoint { .. } => "watchpoint",
+            ExceptionClass::Brk => "brk",
+            ExceptionClass::Other(ec) => return write!(f, "exception class 0x{:02x}", ec),
+        };
+        write!(f, "{}", class)
+    }
+}
+
+/// Fault Status Code of data and instruction aborts (DFSC and
+/// IFSC). Faults during translation record the level of the
+/// translation table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FaultStatus {
+    AddressSize { level: u8 },
+    /// Address is not mapped
+    Translation { level: u8 },
+    AccessFlag { level: u8 },
+    /// Mapping does not allow the access
+    Permission { level: u8 },
+    SyncExternal,
+    /// Memory tag does not match the pointer tag (MTE)
+    TagCheck,
+    SyncExternalWalk { level: u8 },
+    Parity,
+    Alignment,
+    TlbConflict,
+    AtomicUpdate,
+    /// Code which is not known
+    Other(u8),
+}
+
+impl FaultStatus {
+    /// Returns true for faults on addresses which are not mapped
+    pub fn is_translation(self) -> bool {
+        matches!(self, FaultStatus::Translation { .. })
+    }
+
+    /// Returns true for faults on addresses which are mapped
+    /// without the needed access
+    pub fn is_permission(self) -> bool {
+        matches!(self, FaultStatus::Permission { .. })
+    }
+}
+
+impl From<u8> for FaultStatus {
+    fn from(fsc: u8) -> Self {
+        let level = fsc & 0b11;
+        match fsc {
+            0x00..=0x03 => FaultStatus::AddressSize { level },
+            0x04..=0x07 => FaultStatus::Translation { level },
+            0x08..=0x0b => FaultStatus::AccessFlag { level },
+            0x0c..=0x0f => FaultStatus::Permission { level },
+            0x10 => FaultStatus::SyncExternal,
+            0x11 => FaultStatus::TagCheck,
+            0x14..=0x17 => FaultStatus::SyncExternalWalk { level },
+            0x18 => FaultStatus::Parity,
+            0x21 => FaultStatus::Alignment,
+            0x30 => FaultStatus::TlbConflict,
+            0x31 => FaultStatus::AtomicUpdate,
+            _ => FaultStatus::Other(fsc),
+        }
+    }
+}
+
+impl fmt::Display for FaultStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultStatus::AddressSize { level } => {
+                write!(f, "address size fault (level {})", level)
+            }
+            FaultStatus::Translation { level } => {
+                write!(f, "translation fault (level {})", level)
+            }
+            FaultStatus::AccessFlag { level } => write!(f, "access flag fault (level {})", level),
+            FaultStatus::Permission { level } => write!(f, "permission fault (level {})", level),
+            FaultStatus::SyncExternal => write!(f, "synchronous external abort"),
+            FaultStatus::TagCheck => write!(f, "tag check fault"),
+            FaultStatus::SyncExternalWalk { level } => write!(
+                f,
+                "synchronous external abort on table walk (level {})",
+                level
+            ),
+            FaultStatus::Parity => write!(f, "parity or ECC error"),
+            FaultStatus::Alignment => write!(f, "alignment fault"),
+            FaultStatus::TlbConflict => write!(f, "TLB conflict abort"),
+            FaultStatus::AtomicUpdate => write!(f, "unsupported atomic hardware update"),
+            FaultStatus::Other(fsc) => write!(f, "fault status 0x{:02x}", fsc),
+        }
+    }
+}
+
+/// Pointer authentication key
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PacKey {
+    IA,
+    IB,
+    DA,
+    DB,
+}
+
+impl PacKey {
+    /// Key from bit 1 (data key) and bit 0 (B key)
+    fn from_bits(bits: u32) -> Self {
+        match bits & 0b11 {
+            0 => PacKey::IA,
+            1 => PacKey::IB,
+            2 => PacKey::DA,
+            _ => PacKey::DB,
+        }
+    }
+}
+
+impl fmt::Display for PacKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
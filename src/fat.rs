@@ -0,0 +1,178 @@
+use crate::cpu::{CpuSubType, CpuType};
+use crate::endian::{Endian, Reader};
+use crate::error::MachoError;
+use crate::mach_header::MachHeader;
+use crate::macho_ref::MachoRef;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+
+/// Universal binary with 32-bit slice offsets
+pub const FAT_MAGIC: u32 = 0xcafebabe;
+/// Universal binary with 64-bit slice offsets
+pub const FAT_MAGIC_64: u32 = 0xcafebabf;
+
+/// Size of `fat_header`
+const FAT_HEADER_SIZE: usize = 8;
+/// Size of `fat_arch`
+const FAT_ARCH_SIZE: usize = 20;
+/// Size of `fat_arch_64`, which has a trailing reserved field
+const FAT_ARCH_64_SIZE: usize = 32;
+
+/// Architecture slice of a universal binary
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FatArch {
+    /// Processor Type of the slice
+    pub cputype: CpuType,
+    /// Processor Subtype of the slice
+    pub cpu_subtype: CpuSubType,
+    /// Offset of the slice in the file
+    pub offset: u64,
+    /// Size of the slice
+    pub size: u64,
+    /// Alignment of the slice as a power of 2
+    pub align: u32,
+}
+
+impl FatArch {
+    fn read(r: &mut Reader, is_64: bool) -> Self {
+        let cputype = CpuType(r.u32());
+        let cpu_subtype = CpuSubType(r.u32());
+        let (offset, size) = if is_64 {
+            (r.u64(), r.u64())
+        } else {
+            (r.u32() as u64, r.u32() as u64)
+        };
+        let align = r.u32();
+        if is_64 {
+            // reserved
+            r.u32();
+        }
+        Self {
+            cputype,
+            cpu_subtype,
+            offset,
+            size,
+            align,
+        }
+    }
+}
+
+impl fmt::Display for FatArch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cputype:  {} ({})\n\
+            offset:   0x{:08x}\n\
+            size:     0x{:08x}\n\
+            align:    2^{}\n\
+            ",
+            self.cputype, self.cpu_subtype, self.offset, self.size, self.align,
+        )
+    }
+}
+
+/// Universal (fat) binary bundling one Mach-O per architecture.
+/// The fat header is always stored big endian, each slice uses
+/// the byte order selected by its own magic.
+#[derive(Debug, Clone)]
+pub struct FatFile<'a> {
+    /// `FAT_MAGIC` or `FAT_MAGIC_64`
+    pub magic: u32,
+    /// Architecture slices in file order
+    pub arches: Vec<FatArch>,
+    /// Complete universal binary
+    data: &'a [u8],
+}
+
+impl<'a> FatFile<'a> {
+    /// Parses the fat header and architecture table in `data`
+    pub fn parse(data: &'a [u8]) -> Result<Self, MachoError> {
+        let raw_header: [u8; FAT_HEADER_SIZE] = data
+            .get(..FAT_HEADER_SIZE)
+            .and_then(|raw| raw.try_into().ok())
+            .ok_or(MachoError::TruncatedHeader {
+                offset: 0,
+                size: data.len(),
+            })?;
+        let mut r = Reader::new(&raw_header, Endian::Big);
+        let magic = r.u32();
+        let arch_size = match magic {
+            FAT_MAGIC => FAT_ARCH_SIZE,
+            FAT_MAGIC_64 => FAT_ARCH_64_SIZE,
+            magic => return Err(MachoError::BadMagic { offset: 0, magic }),
+        };
+        let nfat_arch = r.u32() as usize;
+
+        let raw_arches = nfat_arch
+            .checked_mul(arch_size)
+            .and_then(|len| len.checked_add(FAT_HEADER_SIZE))
+            .and_then(|end| data.get(FAT_HEADER_SIZE..end))
+            .ok_or(MachoError::TruncatedHeader {
+                offset: 0,
+                size: data.len(),
+            })?;
+        let mut r = Reader::new(raw_arches, Endian::Big);
+        let arches = (0..nfat_arch)
+            .map(|_| FatArch::read(&mut r, magic == FAT_MAGIC_64))
+            .collect();
+
+        Ok(Self {
+            magic,
+            arches,
+            data,
+        })
+    }
+
+    /// Complete universal binary this view borrows from
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Raw bytes of an architecture slice
+    pub fn slice(&self, arch: &FatArch) -> Result<&'a [u8], MachoError> {
+        // Offset of the `fat_arch` entry describing the slice
+        let index = self.arches.iter().position(|a| a == arch).unwrap_or(0);
+        let start = usize::try_from(arch.offset).ok();
+        let end = arch
+            .offset
+            .checked_add(arch.size)
+            .and_then(|end| usize::try_from(end).ok());
+        start
+            .zip(end)
+            .and_then(|(start, end)| self.data.get(start..end))
+            .ok_or(MachoError::SliceOutOfBounds {
+                offset: FAT_HEADER_SIZE + index * self.arch_size(),
+                slice_offset: arch.offset,
+                size: arch.size,
+            })
+    }
+
+    /// Parses an architecture slice. Offsets in the returned view
+    /// are relative to the start of the slice.
+    pub fn macho(&self, arch: &FatArch) -> Result<MachoRef<'a>, MachoError> {
+        MachoRef::parse(self.slice(arch)?)
+    }
+
+    /// Finds the slice built for `cputype` and `cpu_subtype`.
+    /// Capability bits of the subtype are ignored. Falls back to
+    /// any slice of the same `cputype` if no subtype matches.
+    pub fn find(&self, cputype: CpuType, cpu_subtype: CpuSubType) -> Option<&FatArch> {
+        self.arches
+            .iter()
+            .find(|arch| arch.cputype == cputype && arch.cpu_subtype.base() == cpu_subtype.base())
+            .or_else(|| self.arches.iter().find(|arch| arch.cputype == cputype))
+    }
+
+    /// Finds the slice matching the architecture of a core dump
+    pub fn find_for(&self, header: &MachHeader) -> Option<&FatArch> {
+        self.find(header.cputype, header.cpu_subtype)
+    }
+
+    fn arch_size(&self) -> usize {
+        if self.magic == FAT_MAGIC_64 {
+            FAT_ARCH_64_SIZE
+        } else {
+            FAT_ARCH_SIZE
+        }
+    }
+}
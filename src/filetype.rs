@@ -6,13 +6,13 @@ use std::fmt;
 pub struct FileType(pub u32);
 
 const MH_OBJECT: FileType = FileType(1);
-const MH_EXECUTE: FileType = FileType(2);
+pub(crate) const MH_EXECUTE: FileType = FileType(2);
 const MH_FVMLIB: FileType = FileType(3);
 const MH_CORE: FileType = FileType(4);
 const MH_PRELOAD: FileType = FileType(5);
-const MH_DYLIB: FileType = FileType(6);
-const MH_DYLINKER: FileType = FileType(7);
-const MH_BUNDLE: FileType = FileType(8);
+pub(crate) const MH_DYLIB: FileType = FileType(6);
+pub(crate) const MH_DYLINKER: FileType = FileType(7);
+pub(crate) const MH_BUNDLE: FileType = FileType(8);
 const MH_DYLIB_STUB: FileType = FileType(9);
 const MH_DSYM: FileType = FileType(10);
 const MH_KEXT_BUNDLE: FileType = FileType(11);
@@ -0,0 +1,89 @@
+use crate::segment::Segment;
+use std::ops::Range;
+
+/// Virtual memory ranges of all segments sorted by start address,
+/// for lookups in O(log n). Segments may overlap in damaged core
+/// dumps, so each entry also tracks the highest end address seen
+/// up to it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SegmentIndex {
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Entry {
+    start: u64,
+    end: u64,
+    /// Highest `end` of this and all previous entries
+    max_end: u64,
+    /// Position of the segment in `Macho::segments`
+    segment: usize,
+}
+
+impl SegmentIndex {
+    /// Indexes `segments`, skipping segments which map nothing
+    pub(crate) fn new(segments: &[Segment]) -> Self {
+        let mut entries: Vec<Entry> = segments
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| segment.vmsize > 0)
+            .map(|(i, segment)| {
+                let start = segment.vmaddr as u64;
+                let end = start.saturating_add(segment.vmsize as u64);
+                Entry {
+                    start,
+                    end,
+                    max_end: end,
+                    segment: i,
+                }
+            })
+            .collect();
+        entries.sort_by_key(|entry| (entry.start, entry.segment));
+
+        let mut max_end = 0;
+        for entry in &mut entries {
+            max_end = max_end.max(entry.end);
+            entry.max_end = max_end;
+        }
+        Self { entries }
+    }
+
+    /// Index of the segment containing `vaddr`. If segments overlap
+    /// the one starting closest to `vaddr` wins.
+    pub(crate) fn find(&self, vaddr: u64) -> Option<usize> {
+        let end = self.entries.partition_point(|entry| entry.start <= vaddr);
+        self.entries[..end]
+            .iter()
+            .rev()
+            .take_while(|entry| entry.max_end > vaddr)
+            .find(|entry| entry.end > vaddr)
+            .map(|entry| entry.segment)
+    }
+
+    /// Lowest mapped address at or after `vaddr`
+    pub(crate) fn next_mapped(&self, vaddr: u64) -> Option<u64> {
+        if self.find(vaddr).is_some() {
+            return Some(vaddr);
+        }
+        let next = self.entries.partition_point(|entry| entry.start <= vaddr);
+        self.entries.get(next).map(|entry| entry.start)
+    }
+
+    /// End of the highest mapped address range
+    pub(crate) fn end(&self) -> u64 {
+        self.entries.last().map_or(0, |entry| entry.max_end)
+    }
+
+    /// Indices of all segments overlapping `range`, sorted by
+    /// start address
+    pub(crate) fn overlapping(&self, range: Range<u64>) -> impl Iterator<Item = usize> + '_ {
+        let first = self
+            .entries
+            .partition_point(|entry| entry.max_end <= range.start);
+        let last = self.entries.partition_point(|entry| entry.start < range.end);
+        self.entries[first..last.max(first)]
+            .iter()
+            .filter(move |entry| entry.end > range.start)
+            .map(|entry| entry.segment)
+    }
+}
@@ -1,7 +1,7 @@
 //! mach-dump library for parsing Mach-O core dumps taken from macOS and iOS
 //!
 //! # Example
-//! ```rust
+//! ```rust,no_run
 //! use std::path::Path;
 //! use mach_dump::macho::Macho;
 //! fn main() {
@@ -23,10 +23,27 @@
 //! ```
 #![allow(non_snake_case)]
 
-mod cpu;
+pub mod address_space;
+pub mod core_style;
+pub mod cpsr;
+pub mod cpu;
+pub mod endian;
+pub mod error;
+pub mod esr;
+pub mod fat;
 mod filetype;
 mod flag;
-mod load_command;
+mod index;
+pub mod load_command;
 mod mach_header;
 pub mod macho;
+pub mod macho_ref;
+mod mmap;
+pub mod note;
+pub mod region;
 mod segment;
+pub mod sme;
+pub mod thread;
+pub mod thread_state;
+pub mod validate;
+pub mod vm_prot;
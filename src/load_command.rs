@@ -1,3 +1,10 @@
+use crate::cpu::CpuType;
+use crate::endian::{Endian, Reader};
+use crate::error::MachoError;
+use crate::sme::SmeRegisters;
+use crate::thread_state::{
+    ArmDebugState64, ArmExceptionState64, ArmNeonState64, FlavorState, ThreadState,
+};
 use std::convert::TryInto;
 use std::fmt;
 
@@ -141,11 +148,94 @@ impl fmt::Display for LoadCommand {
 }
 
 impl LoadCommand {
-    pub fn new(raw_lc: &[u8; std::mem::size_of::<LoadCommand>()]) -> Self {
-        Self {
-            cmd: LoadCommandType(u32::from_le_bytes(raw_lc[..4].try_into().expect("A"))),
-            cmdsize: u32::from_le_bytes(raw_lc[4..].try_into().expect("B")),
+    /// Parses the load command located at `offset` in `buf` and
+    /// checks that the whole command fits into `buf`
+    pub fn new(buf: &[u8], offset: usize, endian: Endian) -> Result<Self, MachoError> {
+        let raw_lc: &[u8; std::mem::size_of::<LoadCommand>()] = command_bytes(buf, offset)?;
+        let mut r = Reader::new(raw_lc, endian);
+        let lc = Self {
+            cmd: LoadCommandType(r.u32()),
+            cmdsize: r.u32(),
+        };
+
+        let in_bounds = offset
+            .checked_add(lc.cmdsize as usize)
+            .is_some_and(|end| end <= buf.len());
+        if (lc.cmdsize as usize) < std::mem::size_of::<LoadCommand>() || !in_bounds {
+            return Err(MachoError::LoadCommandOutOfBounds {
+                offset,
+                cmdsize: lc.cmdsize,
+            });
         }
+
+        Ok(lc)
+    }
+}
+
+/// Returns the first `N` bytes of the command located at `offset`
+fn command_bytes<const N: usize>(buf: &[u8], offset: usize) -> Result<&[u8; N], MachoError> {
+    Ok(command_slice(buf, offset, N)?.try_into().unwrap())
+}
+
+/// Returns the first `len` bytes of the command located at `offset`
+fn command_slice(buf: &[u8], offset: usize, len: usize) -> Result<&[u8], MachoError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| buf.get(offset..end))
+        .ok_or(MachoError::LoadCommandOutOfBounds {
+            offset,
+            cmdsize: len as u32,
+        })
+}
+
+/// 32-bit core dumps use the SegmentCommand command to store
+/// memory content. Apart from the address and size fields it
+/// matches SegmentCommand64.
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct SegmentCommand {
+    /// Is always SegmentCommand
+    cmd: LoadCommandType,
+    /// Total size of command in bytes
+    cmdsize: u32,
+    /// Never set in core dumps
+    segname: [u8; 16],
+    /// Virtual address where content should be mapped
+    pub vmaddr: u32,
+    /// Amount of memory that should be allocated
+    pub vmsize: u32,
+    /// Offset to where memory contents are stored in the core dump
+    pub fileoff: u32,
+    /// Amount of bytes that should be read from core dump
+    pub filesize: u32,
+    /// Permissions of segment (R/W/E)
+    pub maxprot: i32,
+    /// Alsways same as maxprot
+    pub initprot: i32,
+    /// Always set to 0
+    nsects: u32,
+    /// Flags
+    flags: u32,
+}
+
+impl SegmentCommand {
+    /// Parses the segment command located at `offset` in `buf`
+    pub fn new(buf: &[u8], offset: usize, endian: Endian) -> Result<Self, MachoError> {
+        let raw_sc: &[u8; std::mem::size_of::<SegmentCommand>()] = command_bytes(buf, offset)?;
+        let mut r = Reader::new(&raw_sc[8..], endian);
+        Ok(Self {
+            cmd: LC_SEGMENT,
+            cmdsize: std::mem::size_of::<SegmentCommand>() as u32,
+            segname: r.bytes(),
+            vmaddr: r.u32(),
+            vmsize: r.u32(),
+            fileoff: r.u32(),
+            filesize: r.u32(),
+            maxprot: r.i32(),
+            initprot: r.i32(),
+            nsects: r.u32(),
+            flags: r.u32(),
+        })
     }
 }
 
@@ -155,7 +245,8 @@ impl LoadCommand {
 #[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub struct SegmentCommand64 {
-    /// Is always SegmentCommand64
+    /// SegmentCommand64, or SegmentCommand if widened
+    /// from a 32-bit segment
     cmd: LoadCommandType,
     /// Total size of command in bytes
     cmdsize: u32,
@@ -172,7 +263,7 @@ pub struct SegmentCommand64 {
     /// Permissions of segment (R/W/E)
     pub maxprot: i32,
     /// Alsways same as maxprot
-    initprot: i32,
+    pub initprot: i32,
     /// Always set to 0 
     nsects: u32,
     /// Flags
@@ -180,19 +271,41 @@ pub struct SegmentCommand64 {
 }
 
 impl SegmentCommand64 {
-    pub fn new(raw_sc64: &[u8; std::mem::size_of::<SegmentCommand64>()]) -> Self {
-        Self {
+    /// Parses the segment command located at `offset` in `buf`
+    pub fn new(buf: &[u8], offset: usize, endian: Endian) -> Result<Self, MachoError> {
+        let raw_sc64: &[u8; std::mem::size_of::<SegmentCommand64>()] =
+            command_bytes(buf, offset)?;
+        let mut r = Reader::new(&raw_sc64[8..], endian);
+        Ok(Self {
             cmd: LC_SEGMENT_64,
             cmdsize: std::mem::size_of::<SegmentCommand64>() as u32,
-            segname: raw_sc64[8..24].try_into().unwrap(),
-            vmaddr: u64::from_le_bytes(raw_sc64[24..32].try_into().unwrap()),
-            vmsize: u64::from_le_bytes(raw_sc64[32..40].try_into().unwrap()),
-            fileoff: u64::from_le_bytes(raw_sc64[40..48].try_into().unwrap()),
-            filesize: u64::from_le_bytes(raw_sc64[48..56].try_into().unwrap()),
-            maxprot: i32::from_le_bytes(raw_sc64[56..60].try_into().unwrap()),
-            initprot: i32::from_le_bytes(raw_sc64[60..64].try_into().unwrap()),
-            nsects: u32::from_le_bytes(raw_sc64[64..68].try_into().unwrap()),
-            flags: u32::from_le_bytes(raw_sc64[68..72].try_into().unwrap()),
+            segname: r.bytes(),
+            vmaddr: r.u64(),
+            vmsize: r.u64(),
+            fileoff: r.u64(),
+            filesize: r.u64(),
+            maxprot: r.i32(),
+            initprot: r.i32(),
+            nsects: r.u32(),
+            flags: r.u32(),
+        })
+    }
+}
+
+impl From<SegmentCommand> for SegmentCommand64 {
+    fn from(sc: SegmentCommand) -> Self {
+        Self {
+            cmd: sc.cmd,
+            cmdsize: sc.cmdsize,
+            segname: sc.segname,
+            vmaddr: sc.vmaddr as u64,
+            vmsize: sc.vmsize as u64,
+            fileoff: sc.fileoff as u64,
+            filesize: sc.filesize as u64,
+            maxprot: sc.maxprot,
+            initprot: sc.initprot,
+            nsects: sc.nsects,
+            flags: sc.flags,
         }
     }
 }
@@ -224,128 +337,230 @@ impl fmt::Display for SegmentCommand64 {
     }
 }
 
-/// ArmThreadState64 contains all general purpose registers,
-/// the frame pointer, link register, stack pointer,
-/// program counter, and the current program status register
+/// NoteCommand points to arbitrary data stored in the core
+/// dump, identified by the name of its owner
 #[derive(Copy, Clone, Debug)]
 #[repr(C)]
-pub struct ArmThreadState64 {
-    /// General Purpose Registers
-    pub x: [u64; 29],
-    /// Frame Pointer
-    pub fp: u64,
-    /// Link Register
-    pub lr: u64,
-    /// Stack Pointer
-    pub sp: u64,
-    /// Program Counter
-    pub pc: u64,
-    /// Current Program Status Register
-    pub cpsr: u32,
-    /// Padding
-    pub pad: u32,
+pub struct NoteCommand {
+    /// Always NoteCommand
+    cmd: LoadCommandType,
+    /// Size of this command
+    cmdsize: u32,
+    /// Owner name, padded with NUL bytes
+    pub data_owner: [u8; 16],
+    /// Offset to where the note payload is stored in the core dump
+    pub offset: u64,
+    /// Size of the note payload
+    pub size: u64,
 }
 
-impl fmt::Display for ArmThreadState64 {
+impl NoteCommand {
+    /// Parses the note command located at `offset` in `buf`
+    pub fn new(buf: &[u8], offset: usize, endian: Endian) -> Result<Self, MachoError> {
+        let raw_nc: &[u8; std::mem::size_of::<NoteCommand>()] = command_bytes(buf, offset)?;
+        let mut r = Reader::new(&raw_nc[8..], endian);
+        Ok(Self {
+            cmd: LC_NOTE,
+            cmdsize: std::mem::size_of::<NoteCommand>() as u32,
+            data_owner: r.bytes(),
+            offset: r.u64(),
+            size: r.u64(),
+        })
+    }
+
+    /// Owner name up to the first NUL byte
+    pub fn owner(&self) -> &str {
+        let len = self
+            .data_owner
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.data_owner.len());
+        std::str::from_utf8(&self.data_owner[..len]).unwrap_or("Invalid Name")
+    }
+}
+
+impl fmt::Display for NoteCommand {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "X0:  0x{:016x}   X1:  0x{:016x}   X2:  0x{:016x}   X3:  0x{:016x}\n\
-            X4:  0x{:016x}   X5:  0x{:016x}   X6:  0x{:016x}   X7:  0x{:016x}\n\
-            X8:  0x{:016x}   X9:  0x{:016x}   X10: 0x{:016x}   X11: 0x{:016x}\n\
-            X12: 0x{:016x}   X13: 0x{:016x}   X14: 0x{:016x}   X15: 0x{:016x}\n\
-            X16: 0x{:016x}   X17: 0x{:016x}   X18: 0x{:016x}   X19: 0x{:016x}\n\
-            X20: 0x{:016x}   X21: 0x{:016x}   X22: 0x{:016x}   X23: 0x{:016x}\n\
-            X24: 0x{:016x}   X25: 0x{:016x}   X26: 0x{:016x}   X27: 0x{:016x}\n\
-            X28: 0x{:016x}   FP:  0x{:016x}   LR:  0x{:016x}   SP:  0x{:016x}\n\
-            PC:  0x{:016x}   CPSR: 0x{:016x}\n",
-            self.x[0], 
-            self.x[1], 
-            self.x[2], 
-            self.x[3], 
-            self.x[4], 
-            self.x[5], 
-            self.x[6], 
-            self.x[7], 
-            self.x[8], 
-            self.x[9], 
-            self.x[10], 
-            self.x[11], 
-            self.x[12], 
-            self.x[13], 
-            self.x[14], 
-            self.x[15], 
-            self.x[16], 
-            self.x[17], 
-            self.x[18], 
-            self.x[19], 
-            self.x[20], 
-            self.x[21], 
-            self.x[22], 
-            self.x[23], 
-            self.x[24], 
-            self.x[25], 
-            self.x[26], 
-            self.x[27], 
-            self.x[28], 
-            self.fp, 
-            self.lr, 
-            self.sp, 
-            self.pc, 
-            self.cpsr, 
+            "Owner:\t{}\n\
+        offset:   0x{:08x}\n\
+        size:     0x{:08x}\n\
+        ",
+            self.owner(),
+            self.offset,
+            self.size,
         )
     }
 }
 
-/// ThreadCommand contains general purpose register state 
-/// for each thread
-#[derive(Copy, Clone, Debug)]
+/// ThreadCommand contains the register state of one thread,
+/// stored as a sequence of flavors
+#[derive(Clone, Debug)]
 #[repr(C)]
 pub struct ThreadCommand {
     /// Always ThreadCommand
     cmd: LoadCommandType,
     /// Size of this command
     cmdsize: u32,
-    /// Flavor
-    flavor: u32,
-    /// Amount of u32s in ArmThreadState64
-    count: u32,
-    /// Contains General Purpose Register Information
-    pub state: ArmThreadState64,
+    /// All flavors in command order
+    pub flavors: Vec<ThreadFlavor>,
+}
+
+/// One `(flavor, count, state)` entry of a thread command
+#[derive(Clone, Debug)]
+pub struct ThreadFlavor {
+    /// Flavor of the state
+    pub flavor: u32,
+    /// Amount of u32s in the state
+    pub count: u32,
+    /// Decoded state, or the raw words of unknown flavors
+    pub state: FlavorState,
 }
 
 impl ThreadCommand {
-    pub fn new(raw_tc: &[u8; 16 + std::mem::size_of::<ArmThreadState64>()]) -> Self {
-        let mut regs: [u64; 29] = [0; 29];
-        for i in 0..regs.len() {
-            regs[i] = u64::from_le_bytes(raw_tc[16 + i * 8..16 + (i + 1) * 8].try_into().unwrap());
+    /// Parses the thread command located at `offset` in `buf`.
+    /// Every flavor up to `cmdsize` is decoded according to
+    /// `cputype`, flavors which are not supported are kept as
+    /// raw words.
+    pub fn new(
+        buf: &[u8],
+        offset: usize,
+        endian: Endian,
+        cputype: CpuType,
+    ) -> Result<Self, MachoError> {
+        let raw_tc: &[u8; 8] = command_bytes(buf, offset)?;
+        let cmdsize = Reader::new(&raw_tc[4..], endian).u32();
+        if cmdsize < 8 {
+            return Err(MachoError::LoadCommandOutOfBounds { offset, cmdsize });
         }
+        let command = command_slice(buf, offset, cmdsize as usize)?;
 
-        let reg_offset = 16 + 29 * 8;
+        let mut flavors = Vec::new();
+        let mut pos = 8;
+        while pos + 8 <= command.len() {
+            let mut r = Reader::new(&command[pos..], endian);
+            let flavor = r.u32();
+            let count = r.u32();
+            let raw_state = (count as usize)
+                .checked_mul(4)
+                .and_then(|len| command.get(pos + 8..pos + 8 + len))
+                .ok_or(MachoError::ThreadStateOutOfBounds {
+                    offset: offset + pos,
+                    flavor,
+                    count,
+                })?;
 
-        let arm_thread_state64 = ArmThreadState64 {
-            x: regs,
-            fp: u64::from_le_bytes(raw_tc[reg_offset..reg_offset + 8].try_into().unwrap()),
-            lr: u64::from_le_bytes(raw_tc[reg_offset + 8..reg_offset + 16].try_into().unwrap()),
-            sp: u64::from_le_bytes(raw_tc[reg_offset + 16..reg_offset + 24].try_into().unwrap()),
-            pc: u64::from_le_bytes(raw_tc[reg_offset + 24..reg_offset + 32].try_into().unwrap()),
-            cpsr: u32::from_le_bytes(raw_tc[reg_offset + 32..reg_offset + 36].try_into().unwrap()),
-            pad: u32::from_le_bytes(raw_tc[reg_offset + 36..reg_offset + 40].try_into().unwrap()),
-        };
+            let state = match FlavorState::decoder(cputype, flavor) {
+                Some((size, decode)) if size <= raw_state.len() => {
+                    decode(&mut Reader::new(raw_state, endian))
+                }
+                _ => {
+                    let mut r = Reader::new(raw_state, endian);
+                    FlavorState::Raw((0..count).map(|_| r.u32()).collect())
+                }
+            };
+            flavors.push(ThreadFlavor {
+                flavor,
+                count,
+                state,
+            });
+            pos += 8 + raw_state.len();
+        }
 
-        Self {
+        Ok(Self {
             cmd: LC_THREAD,
-            cmdsize: std::mem::size_of::<ThreadCommand>() as u32,
-            flavor: u32::from_le_bytes(raw_tc[8..12].try_into().unwrap()),
-            count: u32::from_le_bytes(raw_tc[12..16].try_into().unwrap()),
-            state: arm_thread_state64,
-        }
+            cmdsize,
+            flavors,
+        })
+    }
+
+    /// Flavors with their offsets from the start of the command
+    pub(crate) fn flavor_offsets(&self) -> impl Iterator<Item = (usize, &ThreadFlavor)> {
+        self.flavors.iter().scan(8, |pos, flavor| {
+            let offset = *pos;
+            *pos += 8 + flavor.count as usize * 4;
+            Some((offset, flavor))
+        })
+    }
+
+    /// General purpose registers of the thread, taken from the
+    /// first flavor holding them
+    pub fn state(&self) -> Option<&ThreadState> {
+        self.flavors.iter().find_map(|flavor| match &flavor.state {
+            FlavorState::General(state) => Some(state),
+            _ => None,
+        })
+    }
+
+    /// Last exception of ARM64 threads, see
+    /// `ArmExceptionState64::description` for the fault
+    pub fn exception(&self) -> Option<&ArmExceptionState64> {
+        self.flavors.iter().find_map(|flavor| match &flavor.state {
+            FlavorState::ArmException64(exception) => Some(exception),
+            _ => None,
+        })
+    }
+
+    /// Hardware breakpoints and watchpoints of ARM64 threads
+    pub fn debug(&self) -> Option<&ArmDebugState64> {
+        self.flavors.iter().find_map(|flavor| match &flavor.state {
+            FlavorState::ArmDebug64(debug) => Some(&**debug),
+            _ => None,
+        })
+    }
+
+    /// SIMD and floating point registers of ARM64 threads
+    pub fn neon(&self) -> Option<&ArmNeonState64> {
+        self.flavors.iter().find_map(|flavor| match &flavor.state {
+            FlavorState::ArmNeon64(neon) => Some(&**neon),
+            _ => None,
+        })
+    }
+
+    /// SME and streaming SVE registers of ARM64 threads
+    pub fn sme(&self) -> Option<SmeRegisters<'_>> {
+        SmeRegisters::new(&self.flavors)
     }
 }
 
 /// Enum for storing boxed Commands
 #[derive(Debug)]
 pub enum CommandType {
+    SegmentCommand(Box<SegmentCommand>),
     SegmentCommand64(Box<SegmentCommand64>),
     ThreadCommand(Box<ThreadCommand>),
+    NoteCommand(Box<NoteCommand>),
+    /// Command which is not decoded, kept byte for byte
+    Raw {
+        /// Type of the command
+        cmd: LoadCommandType,
+        /// Offset of the command in the file
+        offset: usize,
+        /// Whole command including `cmd` and `cmdsize`
+        bytes: Vec<u8>,
+    },
+}
+
+impl CommandType {
+    /// Returns the segment command of 32-bit and 64-bit
+    /// segments, widened to `SegmentCommand64`
+    pub fn segment(&self) -> Option<SegmentCommand64> {
+        match self {
+            CommandType::SegmentCommand(sc) => Some(SegmentCommand64::from(**sc)),
+            CommandType::SegmentCommand64(sc64) => Some(**sc64),
+            _ => None,
+        }
+    }
+
+    /// Type of the command
+    pub fn cmd(&self) -> LoadCommandType {
+        match self {
+            CommandType::SegmentCommand(_) => LC_SEGMENT,
+            CommandType::SegmentCommand64(_) => LC_SEGMENT_64,
+            CommandType::ThreadCommand(_) => LC_THREAD,
+            CommandType::NoteCommand(_) => LC_NOTE,
+            CommandType::Raw { cmd, .. } => *cmd,
+        }
+    }
 }
@@ -1,9 +1,16 @@
-use crate::cpu::{CpuSubType, CpuType, MH_CIGAM_64, MH_MAGIC_64};
+use crate::cpu::{CpuSubType, CpuType, MH_CIGAM, MH_CIGAM_64, MH_MAGIC, MH_MAGIC_64};
+use crate::endian::{Endian, Reader};
+use crate::error::MachoError;
 use crate::filetype::FileType;
 use crate::flag::Flag;
 use std::convert::TryInto;
 use std::fmt;
 
+/// Size of the 32-bit header, which lacks the reserved field
+const MH_HEADER_SIZE: usize = 28;
+/// Size of the 64-bit header
+const MH_HEADER_64_SIZE: usize = std::mem::size_of::<MachHeader>();
+
 /// Mach Header containing information about target CPU
 /// and architecture, number of load commands and flags of Mach-O
 #[derive(Debug, Copy, Clone)]
@@ -23,41 +30,75 @@ pub struct MachHeader {
     pub sizeofcmds: u32,
     /// Flags
     pub flags: Flag,
-    /// Reserved
+    /// Reserved, only present in 64-bit headers
     pub reserved: u32,
 }
 
 impl MachHeader {
-    pub fn new(raw_header: &[u8; std::mem::size_of::<MachHeader>()]) -> Self {
-        Self {
-            magic: u32::from_ne_bytes(raw_header[0..4].try_into().expect("Failed to get Magic")),
-            cputype: CpuType(u32::from_ne_bytes(
-                raw_header[4..8].try_into().expect("Failed to get CpuType"),
-            )),
-            cpu_subtype: CpuSubType(u32::from_ne_bytes(
-                raw_header[8..12]
-                    .try_into()
-                    .expect("failed to get CPUSubType"),
-            )),
-            filetype: FileType(u32::from_ne_bytes(
-                raw_header[12..16]
-                    .try_into()
-                    .expect("failed to get FileType"),
-            )),
-            ncmds: u32::from_ne_bytes(raw_header[16..20].try_into().expect("failed to get ncmds")),
-            sizeofcmds: u32::from_ne_bytes(
-                raw_header[20..24]
-                    .try_into()
-                    .expect("failed to get sizeofcmds"),
-            ),
-            flags: Flag(u32::from_ne_bytes(
-                raw_header[24..28].try_into().expect("failed to get flags"),
-            )),
-            reserved: u32::from_ne_bytes(
-                raw_header[28..32]
-                    .try_into()
-                    .expect("failed to get reserved"),
-            ),
+    /// Parses the header located at `offset` in `buf`
+    pub fn new(buf: &[u8], offset: usize) -> Result<Self, MachoError> {
+        let available = buf.len().saturating_sub(offset);
+        let raw_magic: [u8; 4] = offset
+            .checked_add(4)
+            .and_then(|end| buf.get(offset..end))
+            .and_then(|raw| raw.try_into().ok())
+            .ok_or(MachoError::TruncatedHeader {
+                offset,
+                size: available,
+            })?;
+
+        // The magic is stored in the byte order of the file, so it
+        // reads as MH_CIGAM(_64) if the file is big endian
+        let magic = u32::from_le_bytes(raw_magic);
+        let (endian, size) = match magic {
+            MH_MAGIC_64 => (Endian::Little, MH_HEADER_64_SIZE),
+            MH_CIGAM_64 => (Endian::Big, MH_HEADER_64_SIZE),
+            MH_MAGIC => (Endian::Little, MH_HEADER_SIZE),
+            MH_CIGAM => (Endian::Big, MH_HEADER_SIZE),
+            magic => return Err(MachoError::BadMagic { offset, magic }),
+        };
+
+        let raw_header = offset
+            .checked_add(size)
+            .and_then(|end| buf.get(offset + 4..end))
+            .ok_or(MachoError::TruncatedHeader {
+                offset,
+                size: available,
+            })?;
+
+        let mut r = Reader::new(raw_header, endian);
+        Ok(Self {
+            magic,
+            cputype: CpuType(r.u32()),
+            cpu_subtype: CpuSubType(r.u32()),
+            filetype: FileType(r.u32()),
+            ncmds: r.u32(),
+            sizeofcmds: r.u32(),
+            flags: Flag(r.u32()),
+            reserved: if size == MH_HEADER_64_SIZE { r.u32() } else { 0 },
+        })
+    }
+
+    /// Byte order of all structures in the file
+    pub fn endian(&self) -> Endian {
+        match self.magic {
+            MH_CIGAM_64 | MH_CIGAM => Endian::Big,
+            _ => Endian::Little,
+        }
+    }
+
+    /// Whether the file uses 64-bit structures
+    pub fn is_64(&self) -> bool {
+        matches!(self.magic, MH_MAGIC_64 | MH_CIGAM_64)
+    }
+
+    /// Size of the header in the file. Load commands
+    /// start right after it.
+    pub fn size(&self) -> usize {
+        if self.is_64() {
+            MH_HEADER_64_SIZE
+        } else {
+            MH_HEADER_SIZE
         }
     }
 }
@@ -67,6 +108,8 @@ impl fmt::Display for MachHeader {
         let magic = match self.magic {
             MH_MAGIC_64 => "64-bit MachO (Little Endian)",
             MH_CIGAM_64 => "64-bit MachO (Big Endian)",
+            MH_MAGIC => "32-bit MachO (Little Endian)",
+            MH_CIGAM => "32-bit MachO (Big Endian)",
             _ => "unknown",
         };
 
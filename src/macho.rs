@@ -1,101 +1,410 @@
-use std::convert::TryInto;
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
 
-use crate::load_command::{
-    ArmThreadState64, CommandType, LoadCommand, SegmentCommand64, ThreadCommand, LC_SEGMENT_64,
-    LC_THREAD,
-};
+use crate::address_space::PointerWidth;
+use crate::core_style::CoreStyle;
+use crate::error::MachoError;
+use crate::load_command::{CommandType, LoadCommand, SegmentCommand64, ThreadCommand};
+use crate::index::SegmentIndex;
 use crate::mach_header::MachHeader;
-use crate::segment::Segment;
+use crate::macho_ref::{file_range, MachoRef};
+use crate::mmap::Data;
+use crate::note::Note;
+use crate::segment::{Content, ReadSeek, Segment};
+use crate::thread::Thread;
+use crate::thread_state::FlavorState;
+use crate::validate::{self, Diagnostic, Severity, ValidationMode};
+use crate::vm_prot::VmProt;
 
 /// Main struct which representes a core dump
 #[derive(Debug)]
 pub struct Macho {
     /// Mach-O Header
     pub header: MachHeader,
-    /// List of Segment64 and Thread Commands
+    /// All load commands in file order. Commands which are not
+    /// decoded yet are kept as `CommandType::Raw`.
     pub load_commands: Vec<CommandType>,
     /// Memory ranges which are stored in the core dump
     pub segments: Vec<Segment>,
+    /// Notes stored in the core dump, decoded with a `NoteRegistry`
+    pub notes: Vec<Note>,
+    /// Problems found while loading. Only contains warnings
+    /// when loaded in strict mode.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Which memory the core dump captured. Detected while loading
+    /// and can be changed if the caller knows better, as it decides
+    /// whether memory which is not stored reads as zeros.
+    pub style: CoreStyle,
+    /// Size of pointers read with `read_ptr`. Detected from the
+    /// header and can be changed for unusual ABIs.
+    pub pointer_width: PointerWidth,
+    /// Address lookup over `segments`, built while loading
+    pub(crate) index: SegmentIndex,
 }
 
 impl Macho {
     /// Loads a core dump from disk and returns the parsed
-    /// struct
-    pub fn load<P: AsRef<Path>>(path: P) -> Option<Self> {
-        let mut f = File::open(path).expect("Could not load Mach-O");
-        let mut contents: Vec<u8> = vec![];
-        f.read_to_end(&mut contents).expect("Read failed");
-        let header: &MachHeader = &MachHeader::new(
-            contents[..std::mem::size_of::<MachHeader>()]
-                .try_into()
-                .expect("Could not load header"),
-        );
-
-        let mut load_commands: Vec<CommandType> = Vec::with_capacity(header.ncmds as usize);
+    /// struct. Any error found while validating rejects the
+    /// core dump.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MachoError> {
+        Self::load_with(path, ValidationMode::Strict)
+    }
+
+    /// Loads a core dump from disk, handling problems according
+    /// to `mode`
+    pub fn load_with<P: AsRef<Path>>(path: P, mode: ValidationMode) -> Result<Self, MachoError> {
+        let mut f = File::open(path).map_err(|source| MachoError::Io { offset: 0, source })?;
+        let data = Data::read(&mut f).map_err(|source| MachoError::Io { offset: 0, source })?;
+        Self::parse_shared(Arc::new(data), mode)
+    }
+
+    /// Maps a core dump into memory instead of reading it. Only
+    /// the header and load commands are touched while loading,
+    /// segment contents are paged in once they are accessed.
+    /// The file must not be modified while it is mapped.
+    pub fn map<P: AsRef<Path>>(path: P) -> Result<Self, MachoError> {
+        Self::map_with(path, ValidationMode::Strict)
+    }
+
+    /// Maps a core dump into memory, handling problems according
+    /// to `mode`
+    pub fn map_with<P: AsRef<Path>>(path: P, mode: ValidationMode) -> Result<Self, MachoError> {
+        let mut f = File::open(path).map_err(|source| MachoError::Io { offset: 0, source })?;
+        let data = Data::map(&mut f).map_err(|source| MachoError::Io { offset: 0, source })?;
+        Self::parse_shared(Arc::new(data), mode)
+    }
+
+    /// Loads a core dump from a seekable source. Only the header
+    /// and load commands are read up front, segment contents are
+    /// read on first access.
+    pub fn from_reader<R: Read + Seek + Send + 'static>(reader: R) -> Result<Self, MachoError> {
+        Self::from_reader_with(reader, ValidationMode::Strict)
+    }
+
+    /// Loads a core dump from a seekable source, handling problems
+    /// according to `mode`
+    pub fn from_reader_with<R: Read + Seek + Send + 'static>(
+        mut reader: R,
+        mode: ValidationMode,
+    ) -> Result<Self, MachoError> {
+        let file_len = reader
+            .seek(SeekFrom::End(0))
+            .and_then(|len| reader.seek(SeekFrom::Start(0)).map(|_| len))
+            .map_err(|source| MachoError::Io { offset: 0, source })?;
+        let commands = read_commands(&mut reader)?;
+
+        let reader: Arc<Mutex<Box<dyn ReadSeek>>> = Arc::new(Mutex::new(Box::new(reader)));
+        Self::parse(&commands, file_len as usize, mode, |_| Content::Lazy {
+            reader: Arc::clone(&reader),
+            cache: OnceLock::new(),
+        })
+    }
+
+    /// Loads a core dump from a source which can not seek, like
+    /// a pipe. The contents of all segments are read in file order
+    /// and kept in memory, bytes between segments are skipped.
+    pub fn from_stream<R: Read>(reader: R) -> Result<Self, MachoError> {
+        Self::from_stream_with(reader, ValidationMode::Strict)
+    }
+
+    /// Loads a core dump from a source which can not seek, handling
+    /// problems according to `mode`
+    pub fn from_stream_with<R: Read>(
+        mut reader: R,
+        mode: ValidationMode,
+    ) -> Result<Self, MachoError> {
+        let commands = read_commands(&mut reader)?;
+
+        // Collect the file ranges of all segments and notes as
+        // sorted, non-overlapping ranges, so each byte is read once
+        let mut ranges: Vec<Range<usize>> = match MachoRef::parse(&commands) {
+            Ok(macho_ref) => macho_ref
+                .load_commands()
+                .filter_map(|lc| lc.ok())
+                .filter_map(|lc| match (lc.segment(), lc.note()) {
+                    (Some(Ok(sc)), _) => Some(file_range(sc.fileoff, sc.filesize, usize::MAX)),
+                    (_, Some(Ok(nc))) => Some(file_range(nc.offset, nc.size, usize::MAX)),
+                    _ => None,
+                })
+                .filter(|range| !range.is_empty())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        // Read the ranges until the stream ends
+        let mut pos = commands.len();
+        let mut buffers: Vec<(usize, Arc<Data>)> = Vec::with_capacity(merged.len());
+        for range in merged {
+            // Segment contents may start inside the load commands
+            let mut buf: Vec<u8> = commands
+                .get(range.start..range.end.min(commands.len()))
+                .unwrap_or_default()
+                .to_vec();
+
+            let start = range.start.max(pos);
+            let skipped = io::copy(
+                &mut (&mut reader).take((start - pos) as u64),
+                &mut io::sink(),
+            )
+            .map_err(|source| MachoError::Io { offset: pos, source })?;
+            pos += skipped as usize;
+            if pos < start {
+                break;
+            }
+
+            let read = (&mut reader)
+                .take(range.end.saturating_sub(start) as u64)
+                .read_to_end(&mut buf)
+                .map_err(|source| MachoError::Io { offset: pos, source })?;
+            pos += read;
+            buffers.push((range.start, Arc::new(Data::Owned(buf))));
+            if pos < range.end {
+                break;
+            }
+        }
+
+        Self::parse(&commands, pos, mode, |range| {
+            // Segments without content may point into a gap
+            // between buffers or past the end of the stream
+            let covers = |(base, data): &&(usize, Arc<Data>)| {
+                *base <= range.start && range.end <= *base + data.len()
+            };
+            match buffers.iter().find(covers) {
+                Some((base, data)) => Content::Shared {
+                    data: Arc::clone(data),
+                    base: *base,
+                },
+                None => Content::Shared {
+                    data: Arc::new(Data::Owned(Vec::new())),
+                    base: range.start,
+                },
+            }
+        })
+    }
+
+    /// Parses a core dump held completely in `contents`. Segments
+    /// borrow their memory content from `contents`.
+    fn parse_shared(contents: Arc<Data>, mode: ValidationMode) -> Result<Self, MachoError> {
+        Self::parse(&contents, contents.len(), mode, |_| Content::Shared {
+            data: Arc::clone(&contents),
+            base: 0,
+        })
+    }
+
+    /// Parses the header and load commands in `commands`, which holds
+    /// at least the start of a file with `file_len` bytes. `content`
+    /// provides the memory content for the file range of a segment.
+    fn parse<F: Fn(Range<usize>) -> Content>(
+        commands: &[u8],
+        file_len: usize,
+        mode: ValidationMode,
+        content: F,
+    ) -> Result<Self, MachoError> {
+        let macho_ref = MachoRef::parse(commands)?;
+        let header = macho_ref.header;
+
+        let mut load_commands: Vec<CommandType> = Vec::new();
         let mut segments: Vec<Segment> = Vec::new();
+        let mut notes: Vec<Note> = Vec::new();
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
 
-        let mut lc_offset = std::mem::size_of::<MachHeader>();
+        // Parsed commands with their offsets for validation
+        let mut lcs: Vec<(usize, LoadCommand)> = Vec::new();
+        let mut seg64_commands: Vec<(usize, SegmentCommand64)> = Vec::new();
 
         // Iterate over each load command
-        for _nlc in 0..header.ncmds as usize {
-            let lc = LoadCommand::new(&contents[lc_offset..lc_offset + 8].try_into().expect("AAA"));
-
-            // Only care for LC_THREAD and LC_SEGMENT_64 right now
-            // because they are only included in the core dump
-            match lc.cmd {
-                LC_THREAD => {
-                    let command_buf: [u8; std::mem::size_of::<ThreadCommand>()] = contents
-                        [lc_offset..lc_offset + std::mem::size_of::<ThreadCommand>()]
-                        .try_into()
-                        .unwrap();
-                    let thread_command = ThreadCommand::new(&command_buf);
-                    load_commands.push(CommandType::ThreadCommand(Box::new(thread_command)));
+        for lc in macho_ref.load_commands() {
+            let lc = match lc {
+                Ok(lc) => lc,
+                Err(err) => {
+                    recover(mode, &mut diagnostics, err)?;
+                    break;
+                }
+            };
+            lcs.push((lc.offset, lc.command));
+
+            // Commands which can not be decoded are kept as raw
+            // bytes so every command shows up in order
+            let command = match lc.decode() {
+                Ok(command) => command,
+                Err(err) => {
+                    recover(mode, &mut diagnostics, err)?;
+                    lc.raw()
+                }
+            };
+
+            // Flavors which are not known for the CPU type are
+            // kept as raw words, strict validation rejects them
+            if let CommandType::ThreadCommand(tc) = &command {
+                for (pos, flavor) in tc.flavor_offsets() {
+                    if FlavorState::decoder(header.cputype, flavor.flavor).is_none() {
+                        recover(
+                            mode,
+                            &mut diagnostics,
+                            MachoError::UnsupportedThreadFlavor {
+                                offset: lc.offset + pos,
+                                flavor: flavor.flavor,
+                            },
+                        )?;
+                    }
+                }
+            }
+
+            if let Some(seg64_command) = command.segment() {
+                seg64_commands.push((lc.offset, seg64_command));
+
+                // Add segment, keeping whatever is left of it
+                // if the file is truncated
+                let range = file_range(seg64_command.fileoff, seg64_command.filesize, file_len);
+                if range.len() as u64 != seg64_command.filesize {
+                    recover(
+                        mode,
+                        &mut diagnostics,
+                        MachoError::SegmentOutOfBounds {
+                            offset: lc.offset,
+                            fileoff: seg64_command.fileoff,
+                            filesize: seg64_command.filesize,
+                        },
+                    )?;
                 }
-                LC_SEGMENT_64 => {
-                    // Create Load Command
-                    let command_buf: [u8; std::mem::size_of::<SegmentCommand64>()] = contents
-                        [lc_offset..lc_offset + std::mem::size_of::<SegmentCommand64>()]
-                        .try_into()
-                        .unwrap();
-                    let seg64_command = SegmentCommand64::new(&command_buf);
-                    load_commands.push(CommandType::SegmentCommand64(Box::new(seg64_command)));
-
-                    // Add segment
-                    let seg_buf: Vec<u8> = contents[seg64_command.fileoff as usize
-                        ..(seg64_command.fileoff + seg64_command.filesize) as usize]
-                        .to_owned();
-                    let segment = Segment::new(
-                        seg64_command.vmaddr as usize,
-                        seg64_command.vmsize as usize,
-                        seg64_command.maxprot as u8,
-                        seg_buf,
-                    );
-                    segments.push(segment);
+                let segment = Segment::new(
+                    seg64_command.vmaddr as usize,
+                    seg64_command.vmsize as usize,
+                    VmProt(seg64_command.initprot),
+                    VmProt(seg64_command.maxprot),
+                    range.start,
+                    range.len(),
+                    content(range),
+                );
+                segments.push(segment);
+            }
+
+            if let CommandType::NoteCommand(note_command) = &command {
+                let range = file_range(note_command.offset, note_command.size, file_len);
+                if range.len() as u64 != note_command.size {
+                    recover(
+                        mode,
+                        &mut diagnostics,
+                        MachoError::NoteOutOfBounds {
+                            offset: lc.offset,
+                            noteoff: note_command.offset,
+                            size: note_command.size,
+                        },
+                    )?;
                 }
-                _ => println!("unsupported lc {:?}", lc.cmd),
+                notes.push(Note::new(
+                    **note_command,
+                    header.endian(),
+                    range.start,
+                    range.len(),
+                    content(range),
+                ));
             }
-            lc_offset += lc.cmdsize as usize;
+            load_commands.push(command);
         }
 
-        Some(Self {
-            header: *header,
+        validate::check_commands(&header, &lcs, &mut diagnostics);
+        validate::check_segments(&seg64_commands, &mut diagnostics);
+        let thread_commands: Vec<(usize, &ThreadCommand)> = lcs
+            .iter()
+            .zip(&load_commands)
+            .filter_map(|((offset, _), command)| match command {
+                CommandType::ThreadCommand(tc) => Some((*offset, &**tc)),
+                _ => None,
+            })
+            .collect();
+        validate::check_threads(header.cputype, &thread_commands, &mut diagnostics);
+
+        if mode == ValidationMode::Strict {
+            if let Some(first) = diagnostics
+                .iter()
+                .find(|diagnostic| diagnostic.severity == Severity::Error)
+            {
+                return Err(MachoError::Validation {
+                    offset: first.offset,
+                    diagnostics,
+                });
+            }
+        }
+
+        let index = SegmentIndex::new(&segments);
+        let mut macho = Self {
+            header,
             load_commands,
             segments,
-        })
+            notes,
+            diagnostics,
+            style: CoreStyle::Full,
+            pointer_width: PointerWidth::of(&header),
+            index,
+        };
+        macho.style = CoreStyle::detect(&macho.segments, &macho.get_threads());
+        Ok(macho)
     }
 
-    /// Return a Vec of all thread states containing the
-    /// general purpose registers in the core dump.
-    pub fn get_threads(&self) -> Vec<ArmThreadState64> {
-        let mut thread_states: Vec<ArmThreadState64> = Vec::new();
+    /// Return a Vec of all threads in the core dump, one for each
+    /// thread command, with their general purpose registers
+    /// decoded for the CPU type. Threads borrow their commands.
+    pub fn get_threads(&self) -> Vec<Thread<'_>> {
+        let mut threads: Vec<Thread> = Vec::new();
         for lc in &self.load_commands {
             if let CommandType::ThreadCommand(tc) = lc {
-                thread_states.push(tc.state)
+                threads.push(Thread {
+                    context: tc.state().copied(),
+                    command: tc,
+                });
             }
         }
-        thread_states
+        threads
     }
 }
+
+/// Returns `err` in strict mode, otherwise records it as
+/// a diagnostic so loading can continue
+fn recover(
+    mode: ValidationMode,
+    diagnostics: &mut Vec<Diagnostic>,
+    err: MachoError,
+) -> Result<(), MachoError> {
+    match (mode, Diagnostic::from_error(&err)) {
+        (ValidationMode::Lenient, Some(diagnostic)) => {
+            diagnostics.push(diagnostic);
+            Ok(())
+        }
+        _ => Err(err),
+    }
+}
+
+/// Reads the header and all load commands from the start of `reader`.
+/// Returns less if the source ends early.
+fn read_commands<R: Read>(reader: &mut R) -> Result<Vec<u8>, MachoError> {
+    // Large enough for 32-bit and 64-bit headers
+    let header_size = std::mem::size_of::<MachHeader>();
+    let mut commands: Vec<u8> = Vec::with_capacity(header_size);
+    reader
+        .take(header_size as u64)
+        .read_to_end(&mut commands)
+        .map_err(|source| MachoError::Io { offset: 0, source })?;
+    let header = MachHeader::new(&commands, 0)?;
+
+    let commands_end = header.size() + header.sizeofcmds as usize;
+    reader
+        .take(commands_end.saturating_sub(commands.len()) as u64)
+        .read_to_end(&mut commands)
+        .map_err(|source| MachoError::Io {
+            offset: header_size,
+            source,
+        })?;
+    Ok(commands)
+}
@@ -0,0 +1,324 @@
+use std::ops::Range;
+
+use crate::error::MachoError;
+use crate::endian::Endian;
+use crate::load_command::{
+    CommandType, LoadCommand, NoteCommand, SegmentCommand, SegmentCommand64, ThreadCommand,
+    LC_NOTE, LC_SEGMENT, LC_SEGMENT_64, LC_THREAD,
+};
+use crate::mach_header::MachHeader;
+use crate::note::{NoteData, NoteRegistry};
+use crate::vm_prot::VmProt;
+
+/// Zero-copy view of a core dump which is already held in
+/// memory. Only the header is parsed up front, load commands
+/// and segments are decoded on demand.
+#[derive(Debug, Copy, Clone)]
+pub struct MachoRef<'a> {
+    /// Mach-O Header
+    pub header: MachHeader,
+    /// Complete core dump
+    data: &'a [u8],
+}
+
+impl<'a> MachoRef<'a> {
+    /// Parses the header of the core dump in `data`
+    pub fn parse(data: &'a [u8]) -> Result<Self, MachoError> {
+        let header = MachHeader::new(data, 0)?;
+        Ok(Self { header, data })
+    }
+
+    /// Complete core dump this view borrows from
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Iterates over all load commands in file order
+    pub fn load_commands(&self) -> LoadCommands<'a> {
+        LoadCommands {
+            data: self.data,
+            header: self.header,
+            offset: self.header.size(),
+            remaining: self.header.ncmds,
+        }
+    }
+
+    /// Iterates over all memory ranges stored in the core dump
+    pub fn segments(&self) -> Segments<'a> {
+        Segments {
+            load_commands: self.load_commands(),
+        }
+    }
+
+    /// Iterates over all notes stored in the core dump
+    pub fn notes(&self) -> Notes<'a> {
+        Notes {
+            load_commands: self.load_commands(),
+        }
+    }
+}
+
+/// Iterator over the load commands of a `MachoRef`. Stops after
+/// the first command which does not fit into the file.
+#[derive(Debug, Clone)]
+pub struct LoadCommands<'a> {
+    data: &'a [u8],
+    header: MachHeader,
+    offset: usize,
+    remaining: u32,
+}
+
+impl<'a> Iterator for LoadCommands<'a> {
+    type Item = Result<LoadCommandRef<'a>, MachoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let offset = self.offset;
+        match LoadCommand::new(self.data, offset, self.header.endian()) {
+            Ok(command) => {
+                self.offset += command.cmdsize as usize;
+                Some(Ok(LoadCommandRef {
+                    offset,
+                    command,
+                    data: self.data,
+                    header: self.header,
+                }))
+            }
+            Err(err) => {
+                // Without a valid size there is no next command
+                self.remaining = 0;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Undecoded load command borrowed from a core dump
+#[derive(Debug, Copy, Clone)]
+pub struct LoadCommandRef<'a> {
+    /// Offset of the command in the file
+    pub offset: usize,
+    /// Type and size of the command
+    pub command: LoadCommand,
+    /// Complete core dump
+    data: &'a [u8],
+    /// Header of the core dump
+    header: MachHeader,
+}
+
+impl<'a> LoadCommandRef<'a> {
+    /// Raw bytes of the whole command
+    pub fn bytes(&self) -> &'a [u8] {
+        &self.data[self.offset..self.offset + self.command.cmdsize as usize]
+    }
+
+    /// Decodes the command. Command types which are not supported
+    /// yet are returned as `CommandType::Raw`.
+    pub fn decode(&self) -> Result<CommandType, MachoError> {
+        let endian = self.header.endian();
+        let command = match self.command.cmd {
+            LC_THREAD => CommandType::ThreadCommand(Box::new(ThreadCommand::new(
+                self.data,
+                self.offset,
+                endian,
+                self.header.cputype,
+            )?)),
+            LC_SEGMENT => CommandType::SegmentCommand(Box::new(SegmentCommand::new(
+                self.data,
+                self.offset,
+                endian,
+            )?)),
+            LC_SEGMENT_64 => CommandType::SegmentCommand64(Box::new(SegmentCommand64::new(
+                self.data,
+                self.offset,
+                endian,
+            )?)),
+            LC_NOTE => CommandType::NoteCommand(Box::new(NoteCommand::new(
+                self.data,
+                self.offset,
+                endian,
+            )?)),
+            _ => self.raw(),
+        };
+        Ok(command)
+    }
+
+    /// Copies the command without decoding it
+    pub fn raw(&self) -> CommandType {
+        CommandType::Raw {
+            cmd: self.command.cmd,
+            offset: self.offset,
+            bytes: self.bytes().to_vec(),
+        }
+    }
+
+    /// Decodes 32-bit and 64-bit segment commands, widened to
+    /// `SegmentCommand64`. Returns `None` for other commands.
+    pub fn segment(&self) -> Option<Result<SegmentCommand64, MachoError>> {
+        let endian = self.header.endian();
+        match self.command.cmd {
+            LC_SEGMENT => Some(
+                SegmentCommand::new(self.data, self.offset, endian).map(SegmentCommand64::from),
+            ),
+            LC_SEGMENT_64 => Some(SegmentCommand64::new(self.data, self.offset, endian)),
+            _ => None,
+        }
+    }
+
+    /// Decodes note commands. Returns `None` for other commands.
+    pub fn note(&self) -> Option<Result<NoteCommand, MachoError>> {
+        match self.command.cmd {
+            LC_NOTE => Some(NoteCommand::new(
+                self.data,
+                self.offset,
+                self.header.endian(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Iterator over the segments of a `MachoRef`
+#[derive(Debug, Clone)]
+pub struct Segments<'a> {
+    load_commands: LoadCommands<'a>,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Result<SegmentRef<'a>, MachoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for lc in &mut self.load_commands {
+            let (lc, seg64_command) = match lc {
+                Ok(lc) => match lc.segment() {
+                    Some(seg64_command) => (lc, seg64_command),
+                    None => continue,
+                },
+                Err(err) => return Some(Err(err)),
+            };
+            return Some(seg64_command.and_then(|sc| SegmentRef::new(lc.data, lc.offset, &sc)));
+        }
+        None
+    }
+}
+
+/// Memory range borrowed from a core dump
+#[derive(Debug, Copy, Clone)]
+pub struct SegmentRef<'a> {
+    /// Memory address of this segment
+    pub vmaddr: usize,
+    /// Memory size of this segment
+    pub vmsize: usize,
+    /// Protection at the time of the dump
+    pub initprot: VmProt,
+    /// Highest protection the segment may be changed to
+    pub maxprot: VmProt,
+    /// Offset of the memory content in the core dump
+    pub fileoff: usize,
+    /// Memory content that should be mapped
+    pub content: &'a [u8],
+}
+
+impl<'a> SegmentRef<'a> {
+    fn new(
+        data: &'a [u8],
+        offset: usize,
+        seg64_command: &SegmentCommand64,
+    ) -> Result<Self, MachoError> {
+        let range = file_range(seg64_command.fileoff, seg64_command.filesize, data.len());
+        if range.len() as u64 != seg64_command.filesize {
+            return Err(MachoError::SegmentOutOfBounds {
+                offset,
+                fileoff: seg64_command.fileoff,
+                filesize: seg64_command.filesize,
+            });
+        }
+        Ok(Self {
+            vmaddr: seg64_command.vmaddr as usize,
+            vmsize: seg64_command.vmsize as usize,
+            initprot: VmProt(seg64_command.initprot),
+            maxprot: VmProt(seg64_command.maxprot),
+            fileoff: range.start,
+            content: &data[range],
+        })
+    }
+}
+
+/// Iterator over the notes of a `MachoRef`
+#[derive(Debug, Clone)]
+pub struct Notes<'a> {
+    load_commands: LoadCommands<'a>,
+}
+
+impl<'a> Iterator for Notes<'a> {
+    type Item = Result<NoteRef<'a>, MachoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for lc in &mut self.load_commands {
+            let (lc, note_command) = match lc {
+                Ok(lc) => match lc.note() {
+                    Some(note_command) => (lc, note_command),
+                    None => continue,
+                },
+                Err(err) => return Some(Err(err)),
+            };
+            return Some(note_command.and_then(|nc| NoteRef::new(&lc, nc)));
+        }
+        None
+    }
+}
+
+/// Note borrowed from a core dump
+#[derive(Debug, Copy, Clone)]
+pub struct NoteRef<'a> {
+    /// Offset of the note command in the file
+    pub offset: usize,
+    /// Owner and file range of the payload
+    pub command: NoteCommand,
+    /// Raw payload of the note
+    pub payload: &'a [u8],
+    /// Byte order of the core dump
+    endian: Endian,
+}
+
+impl<'a> NoteRef<'a> {
+    fn new(lc: &LoadCommandRef<'a>, command: NoteCommand) -> Result<Self, MachoError> {
+        let range = file_range(command.offset, command.size, lc.data.len());
+        if range.len() as u64 != command.size {
+            return Err(MachoError::NoteOutOfBounds {
+                offset: lc.offset,
+                noteoff: command.offset,
+                size: command.size,
+            });
+        }
+        Ok(Self {
+            offset: lc.offset,
+            command,
+            payload: &lc.data[range],
+            endian: lc.header.endian(),
+        })
+    }
+
+    /// Owner name of the note
+    pub fn owner(&self) -> &str {
+        self.command.owner()
+    }
+
+    /// Decodes the payload with the decoder registered for
+    /// its owner
+    pub fn decode(&self, registry: &NoteRegistry) -> Result<NoteData, MachoError> {
+        registry.decode(&self.command, self.payload, self.endian)
+    }
+}
+
+/// File range of `size` bytes at `fileoff`, cut off at the
+/// end of a file with `len` bytes
+pub(crate) fn file_range(fileoff: u64, size: u64, len: usize) -> Range<usize> {
+    let start = fileoff.min(len as u64);
+    let end = fileoff.saturating_add(size).min(len as u64);
+    start as usize..end as usize
+}
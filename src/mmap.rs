@@ -0,0 +1,133 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::ops::Deref;
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+use self::unix::Mmap;
+
+/// Bytes of a core dump, either read into memory or
+/// mapped from disk
+#[derive(Debug)]
+pub(crate) enum Data {
+    Owned(Vec<u8>),
+    #[cfg(all(unix, target_pointer_width = "64"))]
+    Mapped(Mmap),
+}
+
+impl Deref for Data {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Data::Owned(bytes) => bytes,
+            #[cfg(all(unix, target_pointer_width = "64"))]
+            Data::Mapped(map) => map,
+        }
+    }
+}
+
+impl Data {
+    /// Reads the whole file into memory
+    pub(crate) fn read(f: &mut File) -> io::Result<Self> {
+        let mut contents: Vec<u8> = vec![];
+        f.read_to_end(&mut contents)?;
+        Ok(Data::Owned(contents))
+    }
+
+    /// Maps the whole file read-only into memory. Pages are only
+    /// loaded from disk once they are accessed.
+    #[cfg(all(unix, target_pointer_width = "64"))]
+    pub(crate) fn map(f: &mut File) -> io::Result<Self> {
+        let len = f.metadata()?.len() as usize;
+        if len == 0 {
+            return Ok(Data::Owned(Vec::new()));
+        }
+        Mmap::new(f, len).map(Data::Mapped)
+    }
+
+    /// Falls back to reading the file on platforms without mmap
+    #[cfg(not(all(unix, target_pointer_width = "64")))]
+    pub(crate) fn map(f: &mut File) -> io::Result<Self> {
+        Self::read(f)
+    }
+}
+
+#[cfg(all(unix, target_pointer_width = "64"))]
+mod unix {
+    use std::fmt;
+    use std::fs::File;
+    use std::io;
+    use std::ops::Deref;
+    use std::os::raw::{c_int, c_void};
+    use std::os::unix::io::AsRawFd;
+
+    const PROT_READ: c_int = 1;
+    const MAP_PRIVATE: c_int = 2;
+
+    extern "C" {
+        fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: i64,
+        ) -> *mut c_void;
+        fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+
+    /// Read-only private mapping of a whole file
+    pub(crate) struct Mmap {
+        ptr: *const u8,
+        len: usize,
+    }
+
+    // The mapping is read-only and never moves
+    unsafe impl Send for Mmap {}
+    unsafe impl Sync for Mmap {}
+
+    impl Mmap {
+        pub(crate) fn new(f: &File, len: usize) -> io::Result<Self> {
+            let ptr = unsafe {
+                mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    PROT_READ,
+                    MAP_PRIVATE,
+                    f.as_raw_fd(),
+                    0,
+                )
+            };
+            // MAP_FAILED
+            if ptr as isize == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self {
+                ptr: ptr as *const u8,
+                len,
+            })
+        }
+    }
+
+    impl Deref for Mmap {
+        type Target = [u8];
+
+        fn deref(&self) -> &[u8] {
+            unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+        }
+    }
+
+    impl Drop for Mmap {
+        fn drop(&mut self) {
+            unsafe {
+                munmap(self.ptr as *mut c_void, self.len);
+            }
+        }
+    }
+
+    impl fmt::Debug for Mmap {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "Mmap {{ ptr: {:p}, len: 0x{:x} }}", self.ptr, self.len)
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use crate::endian::{Endian, Reader};
+use crate::error::MachoError;
+use crate::load_command::NoteCommand;
+use crate::segment::Content;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Number of bits used for addresses, needed to strip pointer
+/// authentication codes
+pub const ADDRABLE_BITS: &str = "addrable bits";
+/// List of binaries loaded in the process
+pub const ALL_IMAGE_INFOS: &str = "all image infos";
+/// Location of the main binary or kernel
+pub const MAIN_BIN_SPEC: &str = "main bin spec";
+/// JSON dictionary describing the process
+pub const PROCESS_METADATA: &str = "process metadata";
+/// Additional per-thread information
+pub const THREAD_EXTRABITS: &str = "thread extrabits";
+/// Kernel version string
+pub const KERN_VER_STR: &str = "kern ver str";
+/// Location of a single binary
+pub const LOAD_BINARY: &str = "load binary";
+
+/// Decodes the payload of a note. Returns `None` if the payload
+/// is malformed.
+pub type NoteDecoder = fn(payload: &[u8], endian: Endian) -> Option<NoteData>;
+
+/// Decoded payload of a note
+#[derive(Debug)]
+pub enum NoteData {
+    AddrableBits(AddrableBits),
+    AllImageInfos(AllImageInfos),
+    MainBinSpec(MainBinSpec),
+    /// JSON text of the process metadata
+    ProcessMetadata(String),
+    ThreadExtrabits(ThreadExtrabits),
+    KernVerStr(KernVerStr),
+    LoadBinary(LoadBinary),
+    /// Produced by a decoder registered by the caller
+    Custom(Box<dyn Any + Send + Sync>),
+    /// No decoder is registered for the owner
+    Raw(Vec<u8>),
+}
+
+/// Decoders for notes, looked up by owner name. The default
+/// registry knows all owners listed in this module.
+#[derive(Debug, Clone)]
+pub struct NoteRegistry {
+    decoders: HashMap<String, NoteDecoder>,
+}
+
+impl Default for NoteRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(ADDRABLE_BITS, AddrableBits::decode);
+        registry.register(ALL_IMAGE_INFOS, AllImageInfos::decode);
+        registry.register(MAIN_BIN_SPEC, MainBinSpec::decode);
+        registry.register(PROCESS_METADATA, |payload, _| {
+            Some(NoteData::ProcessMetadata(cstring(payload)?))
+        });
+        registry.register(THREAD_EXTRABITS, ThreadExtrabits::decode);
+        registry.register(KERN_VER_STR, KernVerStr::decode);
+        registry.register(LOAD_BINARY, LoadBinary::decode);
+        registry
+    }
+}
+
+impl NoteRegistry {
+    /// Registry without any decoders
+    pub fn empty() -> Self {
+        Self {
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// Registers `decoder` for notes of `owner`, returning the
+    /// decoder it replaces
+    pub fn register(&mut self, owner: &str, decoder: NoteDecoder) -> Option<NoteDecoder> {
+        self.decoders.insert(owner.to_string(), decoder)
+    }
+
+    /// Decodes `payload` of the note `command`. Notes without
+    /// a registered decoder are returned as `NoteData::Raw`.
+    pub fn decode(
+        &self,
+        command: &NoteCommand,
+        payload: &[u8],
+        endian: Endian,
+    ) -> Result<NoteData, MachoError> {
+        match self.decoders.get(command.owner()) {
+            Some(decoder) => decoder(payload, endian).ok_or(MachoError::MalformedNote {
+                offset: command.offset as usize,
+                owner: command.owner().to_string(),
+            }),
+            None => Ok(NoteData::Raw(payload.to_vec())),
+        }
+    }
+}
+
+/// Note of a core dump with its payload
+pub struct Note {
+    /// Owner and file range of the payload
+    pub command: NoteCommand,
+    /// Byte order of the core dump
+    endian: Endian,
+    /// Offset of the payload in the core dump
+    fileoff: usize,
+    /// Amount of payload stored in the core dump
+    size: usize,
+    /// Where the payload comes from
+    content: Content,
+}
+
+impl Note {
+    pub(crate) fn new(
+        command: NoteCommand,
+        endian: Endian,
+        fileoff: usize,
+        size: usize,
+        content: Content,
+    ) -> Self {
+        Self {
+            command,
+            endian,
+            fileoff,
+            size,
+            content,
+        }
+    }
+
+    /// Owner name of the note
+    pub fn owner(&self) -> &str {
+        self.command.owner()
+    }
+
+    /// Raw payload of the note
+    pub fn payload(&self) -> Result<&[u8], MachoError> {
+        self.content.get(self.fileoff, self.size)
+    }
+
+    /// Decodes the payload with the decoder registered for
+    /// its owner
+    pub fn decode(&self, registry: &NoteRegistry) -> Result<NoteData, MachoError> {
+        registry.decode(&self.command, self.payload()?, self.endian)
+    }
+}
+
+impl fmt::Debug for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Note")
+            .field("command", &self.command)
+            .field("endian", &self.endian)
+            .field("fileoff", &self.fileoff)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+/// Payload of `ADDRABLE_BITS`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AddrableBits {
+    pub version: u32,
+    /// Bits used by addresses in the lower half of the address
+    /// space, typically user space
+    pub low: u32,
+    /// Bits used by addresses in the upper half of the address
+    /// space, typically the kernel. Same as `low` before version 4.
+    pub high: u32,
+}
+
+impl AddrableBits {
+    fn decode(payload: &[u8], endian: Endian) -> Option<NoteData> {
+        let mut r = reader(payload, 12, endian)?;
+        let version = r.u32();
+        let low = r.u32();
+        let high = if version >= 4 { r.u32() } else { low };
+        Some(NoteData::AddrableBits(Self { version, low, high }))
+    }
+}
+
+/// Header of `ALL_IMAGE_INFOS`. The image entries are stored
+/// elsewhere in the core dump at `entries_fileoff`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AllImageInfos {
+    pub version: u32,
+    /// Number of images
+    pub imgcount: u32,
+    /// Offset of the first image entry in the core dump
+    pub entries_fileoff: u64,
+    /// Size of each image entry
+    pub entries_size: u32,
+}
+
+impl AllImageInfos {
+    fn decode(payload: &[u8], endian: Endian) -> Option<NoteData> {
+        let mut r = reader(payload, 20, endian)?;
+        Some(NoteData::AllImageInfos(Self {
+            version: r.u32(),
+            imgcount: r.u32(),
+            entries_fileoff: r.u64(),
+            entries_size: r.u32(),
+        }))
+    }
+}
+
+/// Kind of binary described by `MAIN_BIN_SPEC`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BinSpecType(pub u32);
+
+pub const BIN_SPEC_UNKNOWN: BinSpecType = BinSpecType(0);
+pub const BIN_SPEC_KERNEL: BinSpecType = BinSpecType(1);
+pub const BIN_SPEC_USER: BinSpecType = BinSpecType(2);
+pub const BIN_SPEC_STANDALONE: BinSpecType = BinSpecType(3);
+
+impl fmt::Display for BinSpecType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match *self {
+            BIN_SPEC_UNKNOWN => "unknown",
+            BIN_SPEC_KERNEL => "kernel",
+            BIN_SPEC_USER => "user process",
+            BIN_SPEC_STANDALONE => "standalone",
+            _ => "invalid",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// Payload of `MAIN_BIN_SPEC`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MainBinSpec {
+    pub version: u32,
+    pub kind: BinSpecType,
+    /// Load address, `u64::MAX` if unknown
+    pub address: u64,
+    /// Slide, `u64::MAX` if unknown. Only present from version 2.
+    pub slide: u64,
+    pub uuid: [u8; 16],
+    /// Page size as a power of 2, 0 if unknown
+    pub log2_pagesize: u32,
+    /// Platform, only present from version 2
+    pub platform: u32,
+}
+
+impl MainBinSpec {
+    fn decode(payload: &[u8], endian: Endian) -> Option<NoteData> {
+        // Version 2 inserts the slide after the address
+        let version = reader(payload, 4, endian)?.u32();
+        let mut r = reader(payload, if version >= 2 { 48 } else { 36 }, endian)?;
+        r.u32();
+        let kind = BinSpecType(r.u32());
+        let address = r.u64();
+        let slide = if version >= 2 { r.u64() } else { u64::MAX };
+        let uuid = r.bytes();
+        let log2_pagesize = r.u32();
+        let platform = if version >= 2 { r.u32() } else { 0 };
+        Some(NoteData::MainBinSpec(Self {
+            version,
+            kind,
+            address,
+            slide,
+            uuid,
+            log2_pagesize,
+            platform,
+        }))
+    }
+}
+
+/// Payload of `THREAD_EXTRABITS` as written by `lldb`. Every
+/// thread has the same number of 64-bit words, in the order of
+/// the `LC_THREAD` commands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadExtrabits {
+    pub version: u32,
+    /// Number of words stored for each thread
+    pub fields_per_thread: u32,
+    /// Words of all threads, one thread after another
+    pub words: Vec<u64>,
+}
+
+impl ThreadExtrabits {
+    fn decode(payload: &[u8], endian: Endian) -> Option<NoteData> {
+        let mut r = reader(payload, 12, endian)?;
+        let version = r.u32();
+        let fields_per_thread = r.u32();
+        let thread_count = r.u32();
+        if fields_per_thread == 0 {
+            return None;
+        }
+        let len = (fields_per_thread as usize).checked_mul(thread_count as usize)?;
+        let mut r = reader(&payload[12..], len.checked_mul(8)?, endian)?;
+        Some(NoteData::ThreadExtrabits(Self {
+            version,
+            fields_per_thread,
+            words: (0..len).map(|_| r.u64()).collect(),
+        }))
+    }
+
+    /// Number of threads with words in the note
+    pub fn thread_count(&self) -> usize {
+        self.words.len() / self.fields_per_thread as usize
+    }
+
+    /// Words of the `n`th thread
+    pub fn thread(&self, n: usize) -> Option<&[u64]> {
+        self.words
+            .chunks_exact(self.fields_per_thread as usize)
+            .nth(n)
+    }
+
+    /// Thread ID of the `n`th thread, the first word of each
+    /// thread
+    pub fn thread_id(&self, n: usize) -> Option<u64> {
+        self.thread(n).map(|words| words[0])
+    }
+}
+
+/// Payload of `KERN_VER_STR`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernVerStr {
+    pub version: u32,
+    pub string: String,
+}
+
+impl KernVerStr {
+    fn decode(payload: &[u8], endian: Endian) -> Option<NoteData> {
+        let version = reader(payload, 4, endian)?.u32();
+        Some(NoteData::KernVerStr(Self {
+            version,
+            string: cstring(&payload[4..])?,
+        }))
+    }
+}
+
+/// Payload of `LOAD_BINARY`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadBinary {
+    pub version: u32,
+    pub uuid: [u8; 16],
+    /// Load address, `u64::MAX` if unknown
+    pub load_address: u64,
+    /// Slide, `u64::MAX` if unknown
+    pub slide: u64,
+    /// Name of the binary, may be empty
+    pub name: String,
+}
+
+impl LoadBinary {
+    fn decode(payload: &[u8], endian: Endian) -> Option<NoteData> {
+        let mut r = reader(payload, 36, endian)?;
+        Some(NoteData::LoadBinary(Self {
+            version: r.u32(),
+            uuid: r.bytes(),
+            load_address: r.u64(),
+            slide: r.u64(),
+            name: cstring(&payload[36..])?,
+        }))
+    }
+}
+
+/// Reader over a payload holding at least `size` bytes
+fn reader(payload: &[u8], size: usize, endian: Endian) -> Option<Reader<'_>> {
+    if payload.len() < size {
+        return None;
+    }
+    Some(Reader::new(payload, endian))
+}
+
+/// String up to the first NUL byte or the end of `bytes`
+fn cstring(bytes: &[u8]) -> Option<String> {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8(bytes[..len].to_vec()).ok()
+}
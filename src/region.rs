@@ -0,0 +1,341 @@
+use crate::endian::{Endian, Reader};
+use crate::filetype::{MH_BUNDLE, MH_DYLIB, MH_DYLINKER, MH_EXECUTE};
+use crate::load_command::{LC_ID_DYLIB, LC_ID_DYLINKER};
+use crate::mach_header::MachHeader;
+use crate::macho::Macho;
+use crate::macho_ref::MachoRef;
+use crate::segment::Segment;
+use crate::thread::RegisterContext;
+use crate::vm_prot::VmProt;
+use std::convert::TryInto;
+use std::fmt;
+use std::ops::Range;
+
+/// Magic at the start of every dyld shared cache
+const DYLD_CACHE_MAGIC: &[u8; 7] = b"dyld_v1";
+/// Size of `dyld_cache_mapping_info`
+const DYLD_CACHE_MAPPING_SIZE: usize = 32;
+/// Upper bound for load commands of an image in memory
+const MAX_SIZEOFCMDS: u32 = 0x10_0000;
+
+/// What a region of memory is used for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Nothing is known about the region
+    Unknown,
+    /// Address range between regions which is not mapped
+    Gap,
+    /// Mapped without any access, like the guard pages
+    /// around stacks
+    Guard,
+    /// Segment of a Mach-O image, with its install name if known
+    Image(Option<String>),
+    /// Stack of the thread with the given index
+    Stack(usize),
+    /// Heap memory. Only known from labels passed by the caller.
+    Heap,
+    /// dyld shared cache
+    SharedCache,
+}
+
+impl fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            RegionKind::Unknown => "memory",
+            RegionKind::Gap => "unmapped",
+            RegionKind::Guard => "guard",
+            RegionKind::Image(_) => "image",
+            RegionKind::Stack(_) => "stack",
+            RegionKind::Heap => "heap",
+            RegionKind::SharedCache => "shared cache",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// Range of memory with the same protection and use, merged
+/// from adjacent segments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    /// First address of the region
+    pub start: u64,
+    /// Address after the region
+    pub end: u64,
+    /// Protection at the time of the dump
+    pub initprot: VmProt,
+    /// Highest protection the region may be changed to
+    pub maxprot: VmProt,
+    /// Bytes of the region stored in the core dump
+    pub stored: u64,
+    /// What the region is used for
+    pub kind: RegionKind,
+}
+
+impl Region {
+    /// Size of the region in bytes
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+/// Memory map of a core dump in the style of `vmmap`
+#[derive(Debug, Clone, Default)]
+pub struct RegionMap {
+    /// Regions and gaps sorted by address
+    pub regions: Vec<Region>,
+}
+
+impl fmt::Display for RegionMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<14} {:<33} [{:>7} {:>7}] {:<7} DETAIL",
+            "REGION TYPE", "START - END", "VSIZE", "STORED", "PRT/MAX"
+        )?;
+        for region in &self.regions {
+            let prot = match region.kind {
+                RegionKind::Gap => String::new(),
+                _ => format!("{}/{}", region.initprot, region.maxprot),
+            };
+            let detail = match &region.kind {
+                RegionKind::Image(Some(name)) => name.clone(),
+                RegionKind::Stack(thread) => format!("thread {}", thread),
+                _ => String::new(),
+            };
+            let line = format!(
+                "{:<14} {:016x}-{:016x} [{:>7} {:>7}] {:<7} {}",
+                region.kind.to_string(),
+                region.start,
+                region.end,
+                human_size(region.size()),
+                human_size(region.stored),
+                prot,
+                detail
+            );
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl Macho {
+    /// Builds a memory map which merges adjacent segments with the
+    /// same protection and use and lists the gaps between them.
+    /// Stacks are found through the stack pointers of all threads,
+    /// images and the shared cache through headers at the start
+    /// of executable segments. Heap regions are never detected,
+    /// as core dumps do not record the malloc zones; they only
+    /// come from the labels of `region_map_with`.
+    pub fn region_map(&self) -> RegionMap {
+        self.region_map_with(&[])
+    }
+
+    /// Builds a memory map like `region_map`. `labels` mark uses
+    /// known to the caller, like heap ranges, and take precedence
+    /// over the ones found in the core dump.
+    pub fn region_map_with(&self, labels: &[(Range<u64>, RegionKind)]) -> RegionMap {
+        let mut labels = labels.to_vec();
+        labels.extend(self.stack_labels());
+        labels.extend(self.image_labels());
+
+        let mut regions: Vec<Region> = Vec::new();
+        for segment in self.segments_overlapping(0..u64::MAX) {
+            let start = segment.vmaddr as u64;
+            let end = start.saturating_add(segment.vmsize as u64);
+            let kind = labels
+                .iter()
+                .find(|(range, _)| range.contains(&start))
+                .map(|(_, kind)| kind.clone())
+                .unwrap_or(if segment.initprot == VmProt::NONE {
+                    RegionKind::Guard
+                } else {
+                    RegionKind::Unknown
+                });
+
+            if let Some(last) = regions.last_mut() {
+                if last.end == start
+                    && last.initprot == segment.initprot
+                    && last.maxprot == segment.maxprot
+                    && last.kind == kind
+                {
+                    last.end = end;
+                    last.stored += segment.filesize as u64;
+                    continue;
+                }
+                if last.end < start {
+                    let gap = Region {
+                        start: last.end,
+                        end: start,
+                        initprot: VmProt::NONE,
+                        maxprot: VmProt::NONE,
+                        stored: 0,
+                        kind: RegionKind::Gap,
+                    };
+                    regions.push(gap);
+                }
+            }
+            regions.push(Region {
+                start,
+                end,
+                initprot: segment.initprot,
+                maxprot: segment.maxprot,
+                stored: segment.filesize as u64,
+                kind,
+            });
+        }
+        RegionMap { regions }
+    }
+
+    /// Segments containing the stack pointer of a thread
+    fn stack_labels(&self) -> Vec<(Range<u64>, RegionKind)> {
+        self.get_threads()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, thread)| {
+                let segment = self.segment_containing(thread.context?.sp())?;
+                Some((vm_range(segment), RegionKind::Stack(i)))
+            })
+            .collect()
+    }
+
+    /// Segments of Mach-O images and shared caches whose headers
+    /// are found at the start of an executable segment
+    fn image_labels(&self) -> Vec<(Range<u64>, RegionKind)> {
+        let mut labels = Vec::new();
+        for segment in self.segments_with(VmProt::READ_EXECUTE) {
+            let address = segment.vmaddr as u64;
+            let magic = match self.read(address, DYLD_CACHE_MAGIC.len()) {
+                Ok(magic) => magic,
+                Err(_) => continue,
+            };
+            if magic == DYLD_CACHE_MAGIC {
+                labels.extend(
+                    self.shared_cache_ranges(address)
+                        .into_iter()
+                        .map(|range| (range, RegionKind::SharedCache)),
+                );
+            } else if let Some((name, ranges)) = self.image_ranges(address) {
+                labels.extend(
+                    ranges
+                        .into_iter()
+                        .map(|range| (range, RegionKind::Image(name.clone()))),
+                );
+            }
+        }
+        labels
+    }
+
+    /// Install name and slid segment ranges of the image whose
+    /// header is at `address`
+    fn image_ranges(&self, address: u64) -> Option<(Option<String>, Vec<Range<u64>>)> {
+        let header = MachHeader::new(&self.read(address, 32).ok()?, 0).ok()?;
+        let is_image = [MH_EXECUTE, MH_DYLIB, MH_DYLINKER, MH_BUNDLE].contains(&header.filetype);
+        if !is_image || header.endian() != self.header.endian() || header.sizeofcmds > MAX_SIZEOFCMDS
+        {
+            return None;
+        }
+        let commands = self
+            .read(address, header.size() + header.sizeofcmds as usize)
+            .ok()?;
+        let image = MachoRef::parse(&commands).ok()?;
+
+        let mut name = None;
+        let mut segments = Vec::new();
+        for lc in image.load_commands() {
+            let lc = lc.ok()?;
+            if lc.command.cmd == LC_ID_DYLIB || lc.command.cmd == LC_ID_DYLINKER {
+                name = command_string(lc.bytes(), header.endian());
+            } else if let Some(seg64_command) = lc.segment() {
+                segments.push(seg64_command.ok()?);
+            }
+        }
+
+        // The segment mapping the header gives the slide
+        let text = segments
+            .iter()
+            .find(|sc| sc.fileoff == 0 && sc.filesize > 0)?;
+        let slide = address.wrapping_sub(text.vmaddr);
+        let ranges = segments
+            .iter()
+            .filter(|sc| sc.vmsize > 0 && sc.maxprot != 0)
+            .map(|sc| {
+                let start = sc.vmaddr.wrapping_add(slide);
+                start..start.saturating_add(sc.vmsize)
+            })
+            .collect();
+        Some((name, ranges))
+    }
+
+    /// Slid mapping ranges of the shared cache whose header is
+    /// at `address`
+    fn shared_cache_ranges(&self, address: u64) -> Vec<Range<u64>> {
+        let endian = self.header.endian();
+        let (offset, count) = match (
+            self.read_u32(address.saturating_add(0x10)),
+            self.read_u32(address.saturating_add(0x14)),
+        ) {
+            (Ok(offset), Ok(count)) => (offset as u64, count.min(64) as usize),
+            _ => return Vec::new(),
+        };
+        let mappings = address
+            .checked_add(offset)
+            .and_then(|start| self.read(start, count * DYLD_CACHE_MAPPING_SIZE).ok());
+        let mappings = match mappings {
+            Some(mappings) => mappings,
+            None => return Vec::new(),
+        };
+        // address, size and file offset of each mapping
+        let mappings: Vec<(u64, u64, u64)> = mappings
+            .chunks_exact(DYLD_CACHE_MAPPING_SIZE)
+            .map(|m| {
+                let mut r = Reader::new(m, endian);
+                (r.u64(), r.u64(), r.u64())
+            })
+            .collect();
+
+        // The mapping at file offset 0 holds the header
+        let slide = match mappings.iter().find(|(_, _, fileoff)| *fileoff == 0) {
+            Some((vmaddr, _, _)) => address.wrapping_sub(*vmaddr),
+            None => return Vec::new(),
+        };
+        mappings
+            .iter()
+            .map(|(vmaddr, size, _)| {
+                let start = vmaddr.wrapping_add(slide);
+                start..start.saturating_add(*size)
+            })
+            .collect()
+    }
+}
+
+/// Virtual memory range of a segment
+fn vm_range(segment: &Segment) -> Range<u64> {
+    let start = segment.vmaddr as u64;
+    start..start.saturating_add(segment.vmsize as u64)
+}
+
+/// String referenced by the `lc_str` offset right after `cmd`
+/// and `cmdsize`, as used by dylib and dylinker commands
+fn command_string(bytes: &[u8], endian: Endian) -> Option<String> {
+    let offset = endian.u32(bytes.get(8..12)?.try_into().ok()?) as usize;
+    let string = bytes.get(offset..)?;
+    let len = string.iter().position(|&b| b == 0).unwrap_or(string.len());
+    Some(String::from_utf8_lossy(&string[..len]).into_owned())
+}
+
+/// Formats `bytes` in binary units like `vmmap`, e.g. `16K`
+/// or `1.5G`
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "K", "M", "G", "T", "P"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 || size.fract() == 0.0 || size >= 100.0 {
+        format!("{:.0}{}", size, UNITS[unit])
+    } else {
+        format!("{:.1}{}", size, UNITS[unit])
+    }
+}
@@ -1,26 +1,132 @@
+use crate::error::MachoError;
+use crate::mmap::Data;
+use crate::vm_prot::VmProt;
 use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Source which can be read at arbitrary offsets
+pub(crate) trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Where the memory content of a segment comes from
+pub(crate) enum Content {
+    /// Borrowed from bytes holding the file starting at `base`
+    Shared { data: Arc<Data>, base: usize },
+    /// Read from `reader` on first access
+    Lazy {
+        reader: Arc<Mutex<Box<dyn ReadSeek>>>,
+        cache: OnceLock<Vec<u8>>,
+    },
+}
+
+impl Content {
+    /// Returns `len` bytes starting at `fileoff`. Lazy content
+    /// is read on the first call and cached afterwards, so each
+    /// file range needs its own `Content`. Fails if the file
+    /// does not hold the whole range.
+    pub(crate) fn get(&self, fileoff: usize, len: usize) -> Result<&[u8], MachoError> {
+        match self {
+            Content::Shared { data, base } => fileoff
+                .checked_sub(*base)
+                .and_then(|start| data.get(start..start.checked_add(len)?))
+                .ok_or_else(|| MachoError::Io {
+                    offset: fileoff,
+                    source: io::ErrorKind::UnexpectedEof.into(),
+                }),
+            Content::Lazy { reader, cache } => {
+                if let Some(content) = cache.get() {
+                    return Ok(content);
+                }
+                let mut reader = reader.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                let mut content = vec![0; len];
+                reader
+                    .seek(SeekFrom::Start(fileoff as u64))
+                    .and_then(|_| reader.read_exact(&mut content))
+                    .map_err(|source| MachoError::Io {
+                        offset: fileoff,
+                        source,
+                    })?;
+                Ok(cache.get_or_init(|| content))
+            }
+        }
+    }
+}
 
-#[derive(Debug)]
 pub struct Segment {
     /// Memory address of this segment
     pub vmaddr: usize,
     /// Memory size of this segment
     pub vmsize: usize,
-    /// Permissions of segment
-    pub perms: u8,
-    /// Memory content that should be mapped
-    pub content: Vec<u8>,
+    /// Protection at the time of the dump
+    pub initprot: VmProt,
+    /// Highest protection the segment may be changed to
+    pub maxprot: VmProt,
+    /// Offset of the memory content in the core dump
+    pub fileoff: usize,
+    /// Amount of memory content stored in the core dump
+    pub filesize: usize,
+    /// Where the memory content comes from
+    content: Content,
 }
 
 impl Segment {
-    pub fn new(vmaddr: usize, vmsize: usize, perms: u8, content: Vec<u8>) -> Self {
+    pub(crate) fn new(
+        vmaddr: usize,
+        vmsize: usize,
+        initprot: VmProt,
+        maxprot: VmProt,
+        fileoff: usize,
+        filesize: usize,
+        content: Content,
+    ) -> Self {
         Self {
             vmaddr,
             vmsize,
-            perms,
+            initprot,
+            maxprot,
+            fileoff,
+            filesize,
             content,
         }
     }
+
+    /// Memory content that should be mapped. For mapped core
+    /// dumps the pages are only read once they are accessed,
+    /// core dumps loaded from a reader fetch the content on
+    /// the first call.
+    pub fn content(&self) -> Result<&[u8], MachoError> {
+        self.content.get(self.fileoff, self.filesize)
+    }
+
+    /// Virtual addresses whose memory is stored in the core dump.
+    /// The stored bytes always start at `vmaddr`.
+    pub fn captured(&self) -> Range<u64> {
+        let start = self.vmaddr as u64;
+        start..start.saturating_add(self.filesize.min(self.vmsize) as u64)
+    }
+
+    /// Returns true if the core dump stores the whole memory
+    /// of this segment. Partial core dumps leave out memory,
+    /// storing fewer bytes than the segment maps.
+    pub fn is_captured(&self) -> bool {
+        self.filesize >= self.vmsize
+    }
+}
+
+impl fmt::Debug for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Segment")
+            .field("vmaddr", &self.vmaddr)
+            .field("vmsize", &self.vmsize)
+            .field("initprot", &self.initprot)
+            .field("maxprot", &self.maxprot)
+            .field("fileoff", &self.fileoff)
+            .field("filesize", &self.filesize)
+            .finish()
+    }
 }
 
 impl fmt::Display for Segment {
@@ -29,11 +135,12 @@ impl fmt::Display for Segment {
             f,
             "vmaddr: 0x{:08x}\n\
             vmsize:  0x{:08x}\n\
-            perms:   0x{:08x}\n\
+            perms:   {}/{}\n\
             ",
             self.vmaddr,
             self.vmsize,
-            self.perms,
+            self.initprot,
+            self.maxprot,
         )
     }
 }
@@ -0,0 +1,238 @@
+use crate::endian::Reader;
+use crate::load_command::ThreadFlavor;
+use crate::thread_state::{
+    FlavorState, ARM_SME_ZA_STATE1, ARM_SME_ZA_STATE16, ARM_SVE_Z_STATE1, ARM_SVE_Z_STATE2,
+};
+use std::fmt;
+
+/// Largest streaming vector length in bytes. Flavors reserve
+/// room for it, registers only use `svl_b` bytes.
+pub const SVL_B_MAX: usize = 256;
+/// Bytes of ZA stored in each `ARM_SME_ZA_STATE` flavor
+pub const ZA_CHUNK_SIZE: usize = 4096;
+
+/// ArmSmeState contains the SME control registers of ARM64
+/// threads and the streaming vector length
+#[derive(Copy, Clone, Debug)]
+pub struct ArmSmeState {
+    /// Streaming Vector Control Register
+    pub svcr: u64,
+    /// Thread pointer used to lazily save ZA
+    pub tpidr2_el0: u64,
+    /// Streaming vector length in bytes
+    pub svl_b: u16,
+}
+
+impl ArmSmeState {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        Self {
+            svcr: r.u64(),
+            tpidr2_el0: r.u64(),
+            svl_b: r.u16(),
+        }
+    }
+
+    /// Returns true if the thread was in streaming SVE mode
+    /// (`PSTATE.SM`)
+    pub fn streaming_mode(&self) -> bool {
+        self.svcr & 0b01 != 0
+    }
+
+    /// Returns true if the ZA array was enabled (`PSTATE.ZA`)
+    pub fn za_enabled(&self) -> bool {
+        self.svcr & 0b10 != 0
+    }
+}
+
+impl fmt::Display for ArmSmeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "SVCR: 0x{:016x} (SM: {}, ZA: {})   TPIDR2_EL0: 0x{:016x}   SVL: {} bits",
+            self.svcr,
+            self.streaming_mode() as u8,
+            self.za_enabled() as u8,
+            self.tpidr2_el0,
+            self.svl_b as usize * 8
+        )
+    }
+}
+
+/// ArmSveZState contains 16 of the 32 scalable vector registers,
+/// `z0` to `z15` or `z16` to `z31` depending on the flavor
+#[derive(Copy, Clone, Debug)]
+pub struct ArmSveZState {
+    /// Registers padded to the largest vector length
+    pub z: [[u8; SVL_B_MAX]; 16],
+}
+
+impl ArmSveZState {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        let mut z = [[0; SVL_B_MAX]; 16];
+        for reg in z.iter_mut() {
+            *reg = r.bytes();
+        }
+        Self { z }
+    }
+}
+
+/// ArmSvePState contains the 16 scalable predicate registers
+#[derive(Copy, Clone, Debug)]
+pub struct ArmSvePState {
+    /// Registers padded to the largest vector length, one bit
+    /// per byte of a vector
+    pub p: [[u8; SVL_B_MAX / 8]; 16],
+}
+
+impl ArmSvePState {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        let mut p = [[0; SVL_B_MAX / 8]; 16];
+        for reg in p.iter_mut() {
+            *reg = r.bytes();
+        }
+        Self { p }
+    }
+}
+
+/// ArmSmeZaState contains one chunk of the ZA array. Chunk `n`
+/// is stored in flavor `ARM_SME_ZA_STATE1 + n`.
+#[derive(Copy, Clone, Debug)]
+pub struct ArmSmeZaState {
+    pub za: [u8; ZA_CHUNK_SIZE],
+}
+
+impl ArmSmeZaState {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        Self { za: r.bytes() }
+    }
+}
+
+/// ArmSme2State contains the lookup table register of SME2
+#[derive(Copy, Clone, Debug)]
+pub struct ArmSme2State {
+    pub zt0: [u8; 64],
+}
+
+impl ArmSme2State {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        Self { zt0: r.bytes() }
+    }
+}
+
+/// SME and streaming SVE registers of a thread, collected from
+/// all of its SME flavors. Registers are cut to the streaming
+/// vector length of `state`.
+#[derive(Clone, Debug)]
+pub struct SmeRegisters<'a> {
+    /// Control registers and vector length
+    pub state: &'a ArmSmeState,
+    z: [Option<&'a ArmSveZState>; 2],
+    p: Option<&'a ArmSvePState>,
+    za: [Option<&'a ArmSmeZaState>; 16],
+    sme2: Option<&'a ArmSme2State>,
+}
+
+impl<'a> SmeRegisters<'a> {
+    /// Collects the SME flavors of a thread. Returns `None` if
+    /// the thread has no `ARM_SME_STATE`.
+    pub(crate) fn new(flavors: &'a [ThreadFlavor]) -> Option<Self> {
+        let state = flavors.iter().find_map(|flavor| match &flavor.state {
+            FlavorState::ArmSme(state) => Some(state),
+            _ => None,
+        })?;
+        let mut registers = Self {
+            state,
+            z: [None; 2],
+            p: None,
+            za: [None; 16],
+            sme2: None,
+        };
+        for flavor in flavors {
+            match &flavor.state {
+                FlavorState::ArmSveZ(z) => match flavor.flavor {
+                    ARM_SVE_Z_STATE1 => registers.z[0] = Some(&**z),
+                    ARM_SVE_Z_STATE2 => registers.z[1] = Some(&**z),
+                    _ => {}
+                },
+                FlavorState::ArmSveP(p) => registers.p = Some(&**p),
+                FlavorState::ArmSmeZa(za) => {
+                    if let ARM_SME_ZA_STATE1..=ARM_SME_ZA_STATE16 = flavor.flavor {
+                        registers.za[(flavor.flavor - ARM_SME_ZA_STATE1) as usize] = Some(&**za);
+                    }
+                }
+                FlavorState::ArmSme2(sme2) => registers.sme2 = Some(&**sme2),
+                _ => {}
+            }
+        }
+        Some(registers)
+    }
+
+    /// Streaming vector length in bytes, capped at `SVL_B_MAX`
+    pub fn svl_b(&self) -> usize {
+        (self.state.svl_b as usize).min(SVL_B_MAX)
+    }
+
+    /// Scalable vector register `n` with `svl_b` bytes, lane 0
+    /// first
+    pub fn z(&self, n: usize) -> Option<&'a [u8]> {
+        if n >= 32 {
+            return None;
+        }
+        let half = self.z[n / 16]?;
+        Some(&half.z[n % 16][..self.svl_b()])
+    }
+
+    /// Predicate register `n` with one bit per byte of a vector
+    pub fn p(&self, n: usize) -> Option<&'a [u8]> {
+        let p = self.p?;
+        Some(&p.p.get(n)?[..self.svl_b() / 8])
+    }
+
+    /// Row `row` of the ZA array with `svl_b` bytes. ZA has
+    /// `svl_b` rows.
+    pub fn za_row(&self, row: usize) -> Option<&'a [u8]> {
+        let svl_b = self.svl_b();
+        if row >= svl_b {
+            return None;
+        }
+        let start = row * svl_b;
+        let chunk = self.za[start / ZA_CHUNK_SIZE]?;
+        let offset = start % ZA_CHUNK_SIZE;
+        // Rows never straddle chunks, as vector lengths are
+        // powers of two up to `SVL_B_MAX`
+        chunk.za.get(offset..offset + svl_b)
+    }
+
+    /// Lookup table register of SME2
+    pub fn zt0(&self) -> Option<&'a [u8; 64]> {
+        self.sme2.map(|sme2| &sme2.zt0)
+    }
+}
+
+impl fmt::Display for SmeRegisters<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.state)?;
+        let hex = |bytes: &[u8]| -> String {
+            // Most significant byte first, like vector registers
+            bytes
+                .iter()
+                .rev()
+                .map(|byte| format!("{:02x}", byte))
+                .collect()
+        };
+        for n in 0..32 {
+            if let Some(z) = self.z(n) {
+                writeln!(f, "{:<5}0x{}", format!("Z{}:", n), hex(z))?;
+            }
+        }
+        for n in 0..16 {
+            if let Some(p) = self.p(n) {
+                writeln!(f, "{:<5}0x{}", format!("P{}:", n), hex(p))?;
+            }
+        }
+        if let Some(zt0) = self.zt0() {
+            writeln!(f, "ZT0: 0x{}", hex(zt0))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+use crate::load_command::ThreadCommand;
+use crate::sme::SmeRegisters;
+use crate::thread_state::{
+    ArmDebugState64, ArmExceptionState64, ArmNeonState64, ArmThreadState32, ArmThreadState64, ThreadState,
+    X86ThreadState32, X86ThreadState64,
+};
+
+/// Register names of `ArmThreadState64` in field order
+const ARM64_REGISTERS: [&str; 34] = [
+    "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13", "x14",
+    "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27",
+    "x28", "fp", "lr", "sp", "pc", "cpsr",
+];
+
+/// Register names of `ArmThreadState32` in field order
+const ARM_REGISTERS: [&str; 17] = [
+    "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11", "r12", "sp", "lr",
+    "pc", "cpsr",
+];
+
+/// Architecture independent access to the general purpose
+/// registers of a thread
+pub trait RegisterContext {
+    /// Program counter
+    fn pc(&self) -> u64;
+
+    /// Stack pointer
+    fn sp(&self) -> u64;
+
+    /// Frame pointer, `r7` on 32-bit ARM as used by Apple
+    fn fp(&self) -> u64;
+
+    /// Return address of the current function if it is kept in
+    /// a register. On x86 it is on the stack, so this returns
+    /// `None`.
+    fn return_address(&self) -> Option<u64>;
+
+    /// All registers with their names in the order of the
+    /// thread state
+    fn registers(&self) -> Vec<(&'static str, u64)>;
+
+    /// Register with the lowercase `name`, like `x0` or `rip`
+    fn register(&self, name: &str) -> Option<u64> {
+        self.registers()
+            .into_iter()
+            .find(|(register, _)| *register == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Register with the DWARF register `number` of the
+    /// architecture
+    fn dwarf_register(&self, number: u16) -> Option<u64>;
+}
+
+impl RegisterContext for ArmThreadState64 {
+    fn pc(&self) -> u64 {
+        self.pc
+    }
+
+    fn sp(&self) -> u64 {
+        self.sp
+    }
+
+    fn fp(&self) -> u64 {
+        self.fp
+    }
+
+    fn return_address(&self) -> Option<u64> {
+        Some(self.lr)
+    }
+
+    fn registers(&self) -> Vec<(&'static str, u64)> {
+        let values = self
+            .x
+            .iter()
+            .copied()
+            .chain([self.fp, self.lr, self.sp, self.pc, self.cpsr.0 as u64]);
+        ARM64_REGISTERS.iter().copied().zip(values).collect()
+    }
+
+    fn register(&self, name: &str) -> Option<u64> {
+        let name = match name {
+            "x29" => "fp",
+            "x30" => "lr",
+            _ => name,
+        };
+        self.registers()
+            .into_iter()
+            .find(|(register, _)| *register == name)
+            .map(|(_, value)| value)
+    }
+
+    fn dwarf_register(&self, number: u16) -> Option<u64> {
+        match number {
+            0..=28 => Some(self.x[number as usize]),
+            29 => Some(self.fp),
+            30 => Some(self.lr),
+            31 => Some(self.sp),
+            32 => Some(self.pc),
+            _ => None,
+        }
+    }
+}
+
+impl RegisterContext for ArmThreadState32 {
+    fn pc(&self) -> u64 {
+        self.pc as u64
+    }
+
+    fn sp(&self) -> u64 {
+        self.sp as u64
+    }
+
+    fn fp(&self) -> u64 {
+        self.r[7] as u64
+    }
+
+    fn return_address(&self) -> Option<u64> {
+        Some(self.lr as u64)
+    }
+
+    fn registers(&self) -> Vec<(&'static str, u64)> {
+        let values = self
+            .r
+            .iter()
+            .chain([&self.sp, &self.lr, &self.pc, &self.cpsr])
+            .map(|&value| value as u64);
+        ARM_REGISTERS.iter().copied().zip(values).collect()
+    }
+
+    fn dwarf_register(&self, number: u16) -> Option<u64> {
+        match number {
+            0..=12 => Some(self.r[number as usize] as u64),
+            13 => Some(self.sp as u64),
+            14 => Some(self.lr as u64),
+            15 => Some(self.pc as u64),
+            _ => None,
+        }
+    }
+}
+
+impl RegisterContext for X86ThreadState64 {
+    fn pc(&self) -> u64 {
+        self.rip
+    }
+
+    fn sp(&self) -> u64 {
+        self.rsp
+    }
+
+    fn fp(&self) -> u64 {
+        self.rbp
+    }
+
+    fn return_address(&self) -> Option<u64> {
+        None
+    }
+
+    fn registers(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("rax", self.rax),
+            ("rbx", self.rbx),
+            ("rcx", self.rcx),
+            ("rdx", self.rdx),
+            ("rdi", self.rdi),
+            ("rsi", self.rsi),
+            ("rbp", self.rbp),
+            ("rsp", self.rsp),
+            ("r8", self.r8),
+            ("r9", self.r9),
+            ("r10", self.r10),
+            ("r11", self.r11),
+            ("r12", self.r12),
+            ("r13", self.r13),
+            ("r14", self.r14),
+            ("r15", self.r15),
+            ("rip", self.rip),
+            ("rflags", self.rflags),
+            ("cs", self.cs),
+            ("fs", self.fs),
+            ("gs", self.gs),
+        ]
+    }
+
+    fn dwarf_register(&self, number: u16) -> Option<u64> {
+        let value = match number {
+            0 => self.rax,
+            1 => self.rdx,
+            2 => self.rcx,
+            3 => self.rbx,
+            4 => self.rsi,
+            5 => self.rdi,
+            6 => self.rbp,
+            7 => self.rsp,
+            8 => self.r8,
+            9 => self.r9,
+            10 => self.r10,
+            11 => self.r11,
+            12 => self.r12,
+            13 => self.r13,
+            14 => self.r14,
+            15 => self.r15,
+            16 => self.rip,
+            49 => self.rflags,
+            51 => self.cs,
+            54 => self.fs,
+            55 => self.gs,
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+impl RegisterContext for X86ThreadState32 {
+    fn pc(&self) -> u64 {
+        self.eip as u64
+    }
+
+    fn sp(&self) -> u64 {
+        self.esp as u64
+    }
+
+    fn fp(&self) -> u64 {
+        self.ebp as u64
+    }
+
+    fn return_address(&self) -> Option<u64> {
+        None
+    }
+
+    fn registers(&self) -> Vec<(&'static str, u64)> {
+        [
+            ("eax", self.eax),
+            ("ebx", self.ebx),
+            ("ecx", self.ecx),
+            ("edx", self.edx),
+            ("edi", self.edi),
+            ("esi", self.esi),
+            ("ebp", self.ebp),
+            ("esp", self.esp),
+            ("ss", self.ss),
+            ("eflags", self.eflags),
+            ("eip", self.eip),
+            ("cs", self.cs),
+            ("ds", self.ds),
+            ("es", self.es),
+            ("fs", self.fs),
+            ("gs", self.gs),
+        ]
+        .iter()
+        .map(|&(name, value)| (name, value as u64))
+        .collect()
+    }
+
+    fn dwarf_register(&self, number: u16) -> Option<u64> {
+        let value = match number {
+            0 => self.eax,
+            1 => self.ecx,
+            2 => self.edx,
+            3 => self.ebx,
+            4 => self.esp,
+            5 => self.ebp,
+            6 => self.esi,
+            7 => self.edi,
+            8 => self.eip,
+            9 => self.eflags,
+            40 => self.es,
+            41 => self.cs,
+            42 => self.ss,
+            43 => self.ds,
+            44 => self.fs,
+            45 => self.gs,
+            _ => return None,
+        };
+        Some(value as u64)
+    }
+}
+
+impl RegisterContext for ThreadState {
+    fn pc(&self) -> u64 {
+        self.context().pc()
+    }
+
+    fn sp(&self) -> u64 {
+        self.context().sp()
+    }
+
+    fn fp(&self) -> u64 {
+        self.context().fp()
+    }
+
+    fn return_address(&self) -> Option<u64> {
+        self.context().return_address()
+    }
+
+    fn registers(&self) -> Vec<(&'static str, u64)> {
+        self.context().registers()
+    }
+
+    fn register(&self, name: &str) -> Option<u64> {
+        self.context().register(name)
+    }
+
+    fn dwarf_register(&self, number: u16) -> Option<u64> {
+        self.context().dwarf_register(number)
+    }
+}
+
+impl ThreadState {
+    /// State of the architecture as `RegisterContext`
+    fn context(&self) -> &dyn RegisterContext {
+        match self {
+            ThreadState::Arm64(state) => state,
+            ThreadState::Arm(state) => state,
+            ThreadState::X86_64(state) => state,
+            ThreadState::X86(state) => state,
+        }
+    }
+}
+
+/// Thread of the crashed process. The general purpose registers
+/// are decoded for the CPU type of the core dump, further state
+/// like floating point registers is borrowed from the command.
+#[derive(Copy, Clone, Debug)]
+pub struct Thread<'a> {
+    /// General purpose registers. `None` if the command has no
+    /// general purpose flavor which could be decoded, like for
+    /// unknown CPU types.
+    pub context: Option<ThreadState>,
+    /// Thread command holding all flavors of the thread
+    pub command: &'a ThreadCommand,
+}
+
+impl<'a> Thread<'a> {
+    /// Last exception of ARM64 threads
+    pub fn exception(&self) -> Option<&'a ArmExceptionState64> {
+        self.command.exception()
+    }
+
+    /// Hardware breakpoints and watchpoints of ARM64 threads
+    pub fn debug(&self) -> Option<&'a ArmDebugState64> {
+        self.command.debug()
+    }
+
+    /// SIMD and floating point registers of ARM64 threads
+    pub fn neon(&self) -> Option<&'a ArmNeonState64> {
+        self.command.neon()
+    }
+
+    /// SME and streaming SVE registers of ARM64 threads
+    pub fn sme(&self) -> Option<SmeRegisters<'a>> {
+        self.command.sme()
+    }
+}
@@ -0,0 +1,990 @@
+use crate::cpu::{
+    CpuType, CPU_TYPE_ARM, CPU_TYPE_ARM64, CPU_TYPE_ARM64_32, CPU_TYPE_X86, CPU_TYPE_X86_64,
+};
+use crate::cpsr::Cpsr;
+use crate::endian::Reader;
+use crate::esr::{Esr, ExceptionClass};
+use crate::sme::{ArmSme2State, ArmSmeState, ArmSmeZaState, ArmSvePState, ArmSveZState};
+use std::fmt;
+
+/// Thread state flavor of `ArmThreadState32`
+pub const ARM_THREAD_STATE: u32 = 1;
+/// Thread state flavor of `ArmThreadState64`
+pub const ARM_THREAD_STATE64: u32 = 6;
+/// Thread state flavor of `ArmExceptionState64`
+pub const ARM_EXCEPTION_STATE64: u32 = 7;
+/// Thread state flavor of `ArmDebugState64`
+pub const ARM_DEBUG_STATE64: u32 = 15;
+/// Thread state flavor of `ArmNeonState64`
+pub const ARM_NEON_STATE64: u32 = 17;
+/// Thread state flavor of `ArmSmeState`
+pub const ARM_SME_STATE: u32 = 28;
+/// Thread state flavor of `ArmSveZState` with `z0` to `z15`
+pub const ARM_SVE_Z_STATE1: u32 = 29;
+/// Thread state flavor of `ArmSveZState` with `z16` to `z31`
+pub const ARM_SVE_Z_STATE2: u32 = 30;
+/// Thread state flavor of `ArmSvePState`
+pub const ARM_SVE_P_STATE: u32 = 31;
+/// Thread state flavor of the first `ArmSmeZaState`
+pub const ARM_SME_ZA_STATE1: u32 = 32;
+/// Thread state flavor of the last `ArmSmeZaState`
+pub const ARM_SME_ZA_STATE16: u32 = 47;
+/// Thread state flavor of `ArmSme2State`
+pub const ARM_SME2_STATE: u32 = 48;
+/// Thread state flavor of `X86ThreadState32`
+pub const X86_THREAD_STATE32: u32 = 1;
+/// Thread state flavor of `X86ThreadState64`
+pub const X86_THREAD_STATE64: u32 = 4;
+/// Thread state flavor of `X86FloatState64`
+pub const X86_FLOAT_STATE64: u32 = 5;
+/// Thread state flavor of `X86ExceptionState64`
+pub const X86_EXCEPTION_STATE64: u32 = 6;
+/// Thread state flavor of `X86AvxState64`
+pub const X86_AVX_STATE64: u32 = 17;
+
+/// Size of `arm_neon_state64` without its trailing padding
+const ARM_NEON_STATE64_SIZE: usize = 32 * 16 + 8;
+/// Size of `arm_sme_state` without its trailing padding
+const ARM_SME_STATE_SIZE: usize = 8 + 8 + 2;
+/// Size of `x86_float_state64` including reserved fields
+const X86_FLOAT_STATE64_SIZE: usize = 524;
+/// Size of `x86_avx_state64` including reserved fields
+const X86_AVX_STATE64_SIZE: usize = 844;
+
+/// General purpose register state of a thread. Which state
+/// is used depends on the CPU type of the core dump.
+#[derive(Copy, Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum ThreadState {
+    Arm64(ArmThreadState64),
+    Arm(ArmThreadState32),
+    X86_64(X86ThreadState64),
+    X86(X86ThreadState32),
+}
+
+/// State of one flavor in a thread command
+#[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum FlavorState {
+    /// General purpose registers
+    General(ThreadState),
+    /// Last exception of an ARM64 thread
+    ArmException64(ArmExceptionState64),
+    /// Hardware breakpoints and watchpoints of ARM64 threads
+    ArmDebug64(Box<ArmDebugState64>),
+    /// SIMD and floating point registers of ARM64 threads
+    ArmNeon64(Box<ArmNeonState64>),
+    /// SME control registers of ARM64 threads
+    ArmSme(ArmSmeState),
+    /// Half of the streaming SVE vector registers of ARM64
+    /// threads
+    ArmSveZ(Box<ArmSveZState>),
+    /// Streaming SVE predicate registers of ARM64 threads
+    ArmSveP(Box<ArmSvePState>),
+    /// Chunk of the SME ZA array of ARM64 threads
+    ArmSmeZa(Box<ArmSmeZaState>),
+    /// SME2 lookup table register of ARM64 threads
+    ArmSme2(Box<ArmSme2State>),
+    /// x87, MMX and SSE registers of x86_64 threads
+    X86Float64(Box<X86FloatState64>),
+    /// x87, MMX, SSE and AVX registers of x86_64 threads
+    X86Avx64(Box<X86AvxState64>),
+    /// Last exception of an x86_64 thread
+    X86Exception64(X86ExceptionState64),
+    /// Flavor which is not decoded, kept as raw words
+    Raw(Vec<u32>),
+}
+
+/// Decodes the state of a flavor from the command
+pub(crate) type Decoder = fn(&mut Reader) -> FlavorState;
+
+impl FlavorState {
+    /// Size in bytes and decoder of the state with `flavor` on
+    /// `cputype`. Returns `None` if the flavor is not supported.
+    pub(crate) fn decoder(cputype: CpuType, flavor: u32) -> Option<(usize, Decoder)> {
+        // arm64_32 only narrows pointers, threads keep the
+        // ARM64 register state
+        let cputype = if cputype == CPU_TYPE_ARM64_32 {
+            CPU_TYPE_ARM64
+        } else {
+            cputype
+        };
+        match (cputype, flavor) {
+            (CPU_TYPE_ARM64, ARM_THREAD_STATE64) => Some((
+                std::mem::size_of::<ArmThreadState64>(),
+                |r| FlavorState::General(ThreadState::Arm64(ArmThreadState64::read(r))),
+            )),
+            (CPU_TYPE_ARM64, ARM_EXCEPTION_STATE64) => Some((
+                std::mem::size_of::<ArmExceptionState64>(),
+                |r| FlavorState::ArmException64(ArmExceptionState64::read(r)),
+            )),
+            (CPU_TYPE_ARM64, ARM_DEBUG_STATE64) => Some((
+                std::mem::size_of::<ArmDebugState64>(),
+                |r| FlavorState::ArmDebug64(Box::new(ArmDebugState64::read(r))),
+            )),
+            (CPU_TYPE_ARM64, ARM_NEON_STATE64) => Some((ARM_NEON_STATE64_SIZE, |r| {
+                FlavorState::ArmNeon64(Box::new(ArmNeonState64::read(r)))
+            })),
+            (CPU_TYPE_ARM64, ARM_SME_STATE) => Some((ARM_SME_STATE_SIZE, |r| {
+                FlavorState::ArmSme(ArmSmeState::read(r))
+            })),
+            (CPU_TYPE_ARM64, ARM_SVE_Z_STATE1) | (CPU_TYPE_ARM64, ARM_SVE_Z_STATE2) => Some((
+                std::mem::size_of::<ArmSveZState>(),
+                |r| FlavorState::ArmSveZ(Box::new(ArmSveZState::read(r))),
+            )),
+            (CPU_TYPE_ARM64, ARM_SVE_P_STATE) => Some((
+                std::mem::size_of::<ArmSvePState>(),
+                |r| FlavorState::ArmSveP(Box::new(ArmSvePState::read(r))),
+            )),
+            (CPU_TYPE_ARM64, ARM_SME_ZA_STATE1..=ARM_SME_ZA_STATE16) => Some((
+                std::mem::size_of::<ArmSmeZaState>(),
+                |r| FlavorState::ArmSmeZa(Box::new(ArmSmeZaState::read(r))),
+            )),
+            (CPU_TYPE_ARM64, ARM_SME2_STATE) => Some((
+                std::mem::size_of::<ArmSme2State>(),
+                |r| FlavorState::ArmSme2(Box::new(ArmSme2State::read(r))),
+            )),
+            (CPU_TYPE_ARM, ARM_THREAD_STATE) => Some((
+                std::mem::size_of::<ArmThreadState32>(),
+                |r| FlavorState::General(ThreadState::Arm(ArmThreadState32::read(r))),
+            )),
+            (CPU_TYPE_X86_64, X86_THREAD_STATE64) => Some((
+                std::mem::size_of::<X86ThreadState64>(),
+                |r| FlavorState::General(ThreadState::X86_64(X86ThreadState64::read(r))),
+            )),
+            (CPU_TYPE_X86_64, X86_FLOAT_STATE64) => Some((X86_FLOAT_STATE64_SIZE, |r| {
+                FlavorState::X86Float64(Box::new(X86FloatState64::read(r)))
+            })),
+            (CPU_TYPE_X86_64, X86_AVX_STATE64) => Some((X86_AVX_STATE64_SIZE, |r| {
+                FlavorState::X86Avx64(Box::new(X86AvxState64::read(r)))
+            })),
+            (CPU_TYPE_X86_64, X86_EXCEPTION_STATE64) => Some((
+                std::mem::size_of::<X86ExceptionState64>(),
+                |r| FlavorState::X86Exception64(X86ExceptionState64::read(r)),
+            )),
+            (CPU_TYPE_X86, X86_THREAD_STATE32) => Some((
+                std::mem::size_of::<X86ThreadState32>(),
+                |r| FlavorState::General(ThreadState::X86(X86ThreadState32::read(r))),
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ThreadState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThreadState::Arm64(state) => write!(f, "{}", state),
+            ThreadState::Arm(state) => write!(f, "{}", state),
+            ThreadState::X86_64(state) => write!(f, "{}", state),
+            ThreadState::X86(state) => write!(f, "{}", state),
+        }
+    }
+}
+
+/// ArmThreadState64 contains all general purpose registers,
+/// the frame pointer, link register, stack pointer,
+/// program counter, and the current program status register
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct ArmThreadState64 {
+    /// General Purpose Registers
+    pub x: [u64; 29],
+    /// Frame Pointer
+    pub fp: u64,
+    /// Link Register
+    pub lr: u64,
+    /// Stack Pointer
+    pub sp: u64,
+    /// Program Counter
+    pub pc: u64,
+    /// Current Program Status Register
+    pub cpsr: Cpsr,
+    /// Padding
+    pub pad: u32,
+}
+
+impl ArmThreadState64 {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        let mut regs: [u64; 29] = [0; 29];
+        for reg in regs.iter_mut() {
+            *reg = r.u64();
+        }
+
+        Self {
+            x: regs,
+            fp: r.u64(),
+            lr: r.u64(),
+            sp: r.u64(),
+            pc: r.u64(),
+            cpsr: Cpsr(r.u32()),
+            pad: r.u32(),
+        }
+    }
+}
+
+impl fmt::Display for ArmThreadState64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "X0:  0x{:016x}   X1:  0x{:016x}   X2:  0x{:016x}   X3:  0x{:016x}\n\
+            X4:  0x{:016x}   X5:  0x{:016x}   X6:  0x{:016x}   X7:  0x{:016x}\n\
+            X8:  0x{:016x}   X9:  0x{:016x}   X10: 0x{:016x}   X11: 0x{:016x}\n\
+            X12: 0x{:016x}   X13: 0x{:016x}   X14: 0x{:016x}   X15: 0x{:016x}\n\
+            X16: 0x{:016x}   X17: 0x{:016x}   X18: 0x{:016x}   X19: 0x{:016x}\n\
+            X20: 0x{:016x}   X21: 0x{:016x}   X22: 0x{:016x}   X23: 0x{:016x}\n\
+            X24: 0x{:016x}   X25: 0x{:016x}   X26: 0x{:016x}   X27: 0x{:016x}\n\
+            X28: 0x{:016x}   FP:  0x{:016x}   LR:  0x{:016x}   SP:  0x{:016x}\n\
+            PC:  0x{:016x}   CPSR: 0x{:08x} ({})\n",
+            self.x[0], 
+            self.x[1], 
+            self.x[2], 
+            self.x[3], 
+            self.x[4], 
+            self.x[5], 
+            self.x[6], 
+            self.x[7], 
+            self.x[8], 
+            self.x[9], 
+            self.x[10], 
+            self.x[11], 
+            self.x[12], 
+            self.x[13], 
+            self.x[14], 
+            self.x[15], 
+            self.x[16], 
+            self.x[17], 
+            self.x[18], 
+            self.x[19], 
+            self.x[20], 
+            self.x[21], 
+            self.x[22], 
+            self.x[23], 
+            self.x[24], 
+            self.x[25], 
+            self.x[26], 
+            self.x[27], 
+            self.x[28], 
+            self.fp, 
+            self.lr, 
+            self.sp, 
+            self.pc, 
+            self.cpsr.0,
+            self.cpsr,
+        )
+    }
+}
+
+/// ArmExceptionState64 describes the last exception taken
+/// by an ARM64 thread
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct ArmExceptionState64 {
+    /// Fault Address Register
+    pub far: u64,
+    /// Exception Syndrome Register
+    pub esr: Esr,
+    /// Number of the exception taken
+    pub exception: u32,
+}
+
+impl ArmExceptionState64 {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        Self {
+            far: r.u64(),
+            esr: Esr(r.u32()),
+            exception: r.u32(),
+        }
+    }
+
+    /// Address which caused the exception, if the exception
+    /// records one
+    pub fn fault_address(&self) -> Option<u64> {
+        let has_address = match self.esr.class() {
+            ExceptionClass::DataAbort { .. } | ExceptionClass::InstructionAbort { .. } => {
+                self.esr.far_valid() == Some(true)
+            }
+            ExceptionClass::PcAlignment | ExceptionClass::Watchpoint { .. } => true,
+            _ => false,
+        };
+        if has_address {
+            Some(self.far)
+        } else {
+            None
+        }
+    }
+
+    /// Human readable description of the fault, like
+    /// `data abort: translation fault (level 3) on read at 0x0`
+    pub fn description(&self) -> String {
+        match self.fault_address() {
+            Some(address) => format!("{} at 0x{:x}", self.esr, address),
+            None => self.esr.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ArmExceptionState64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "FAR: 0x{:016x}   ESR: 0x{:08x}   EXCEPTION: 0x{:08x}\n\
+            {}\n",
+            self.far,
+            self.esr.0,
+            self.exception,
+            self.description(),
+        )
+    }
+}
+
+/// ArmDebugState64 contains the hardware breakpoint and
+/// watchpoint registers of ARM64 threads, which are set while
+/// the process is debugged
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct ArmDebugState64 {
+    /// Breakpoint Value Registers
+    pub bvr: [u64; 16],
+    /// Breakpoint Control Registers
+    pub bcr: [u64; 16],
+    /// Watchpoint Value Registers
+    pub wvr: [u64; 16],
+    /// Watchpoint Control Registers
+    pub wcr: [u64; 16],
+    /// Monitor Debug System Control Register
+    pub mdscr_el1: u64,
+}
+
+impl ArmDebugState64 {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        let mut read_array = || {
+            let mut regs = [0; 16];
+            for reg in regs.iter_mut() {
+                *reg = r.u64();
+            }
+            regs
+        };
+        let bvr = read_array();
+        let bcr = read_array();
+        let wvr = read_array();
+        let wcr = read_array();
+
+        Self {
+            bvr,
+            bcr,
+            wvr,
+            wcr,
+            mdscr_el1: r.u64(),
+        }
+    }
+
+    /// Enabled hardware breakpoints
+    pub fn breakpoints(&self) -> Vec<Breakpoint> {
+        (0..self.bcr.len())
+            .filter(|&i| self.bcr[i] & 1 != 0)
+            .map(|i| Breakpoint {
+                index: i,
+                address: self.bvr[i],
+            })
+            .collect()
+    }
+
+    /// Enabled hardware watchpoints
+    pub fn watchpoints(&self) -> Vec<Watchpoint> {
+        (0..self.wcr.len())
+            .filter(|&i| self.wcr[i] & 1 != 0)
+            .map(|i| {
+                let wcr = self.wcr[i];
+                // Load/Store Control
+                let lsc = (wcr >> 3) & 0b11;
+                // Either a power of two range given by MASK, or
+                // the bytes of a doubleword selected by BAS
+                let mask = (wcr >> 24) & 0x1f;
+                let bas = ((wcr >> 5) & 0xff) as u8;
+                let (address, size) = if mask != 0 {
+                    let size: u64 = 1 << mask;
+                    (self.wvr[i] & !(size - 1), size)
+                } else {
+                    let first = bas.trailing_zeros() as u64;
+                    let size = (bas >> first.min(7)).trailing_ones() as u64;
+                    // An empty BAS selects no byte, so the address
+                    // may point past the doubleword
+                    ((self.wvr[i] & !7).wrapping_add(first), size)
+                };
+                Watchpoint {
+                    index: i,
+                    address,
+                    size,
+                    read: lsc & 0b01 != 0,
+                    write: lsc & 0b10 != 0,
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for ArmDebugState64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "MDSCR_EL1: 0x{:016x}", self.mdscr_el1)?;
+        for breakpoint in self.breakpoints() {
+            writeln!(f, "{}", breakpoint)?;
+        }
+        for watchpoint in self.watchpoints() {
+            writeln!(f, "{}", watchpoint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Enabled hardware breakpoint
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// Number of the breakpoint register
+    pub index: usize,
+    /// Address of the instruction
+    pub address: u64,
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "breakpoint {}: 0x{:016x}", self.index, self.address)
+    }
+}
+
+/// Enabled hardware watchpoint
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    /// Number of the watchpoint register
+    pub index: usize,
+    /// First watched address
+    pub address: u64,
+    /// Number of watched bytes
+    pub size: u64,
+    /// Triggers on loads
+    pub read: bool,
+    /// Triggers on stores
+    pub write: bool,
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "watchpoint {}: 0x{:016x} size {} {}{}",
+            self.index,
+            self.address,
+            self.size,
+            if self.read { 'r' } else { '-' },
+            if self.write { 'w' } else { '-' }
+        )
+    }
+}
+
+/// ArmNeonState64 contains the SIMD and floating point
+/// registers of ARM64 threads
+#[derive(Copy, Clone, Debug)]
+pub struct ArmNeonState64 {
+    /// Vector Registers, shared with the scalar floating
+    /// point registers
+    pub v: [VectorRegister; 32],
+    /// Floating-point Status Register
+    pub fpsr: u32,
+    /// Floating-point Control Register
+    pub fpcr: u32,
+}
+
+impl ArmNeonState64 {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        let mut v = [VectorRegister(0); 32];
+        for reg in v.iter_mut() {
+            *reg = VectorRegister(r.u128());
+        }
+
+        Self {
+            v,
+            fpsr: r.u32(),
+            fpcr: r.u32(),
+        }
+    }
+}
+
+impl fmt::Display for ArmNeonState64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pair) in self.v.chunks(2).enumerate() {
+            writeln!(
+                f,
+                "{:<4} 0x{:032x}   {:<4} 0x{:032x}",
+                format!("V{}:", 2 * i),
+                pair[0].0,
+                format!("V{}:", 2 * i + 1),
+                pair[1].0
+            )?;
+        }
+        writeln!(f, "FPSR: 0x{:08x}   FPCR: 0x{:08x}", self.fpsr, self.fpcr)
+    }
+}
+
+/// 128-bit SIMD register. Lane 0 holds the least significant
+/// bits, like `v0.s[0]` in assembly.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct VectorRegister(pub u128);
+
+impl VectorRegister {
+    /// 16 lanes of 8 bits, like `v0.16b`
+    pub fn u8s(self) -> [u8; 16] {
+        self.0.to_le_bytes()
+    }
+
+    /// 8 lanes of 16 bits, like `v0.8h`
+    pub fn u16s(self) -> [u16; 8] {
+        let mut lanes = [0; 8];
+        for (i, lane) in lanes.iter_mut().enumerate() {
+            *lane = (self.0 >> (16 * i)) as u16;
+        }
+        lanes
+    }
+
+    /// 4 lanes of 32 bits, like `v0.4s`
+    pub fn u32s(self) -> [u32; 4] {
+        let mut lanes = [0; 4];
+        for (i, lane) in lanes.iter_mut().enumerate() {
+            *lane = (self.0 >> (32 * i)) as u32;
+        }
+        lanes
+    }
+
+    /// 2 lanes of 64 bits, like `v0.2d`
+    pub fn u64s(self) -> [u64; 2] {
+        [self.0 as u64, (self.0 >> 64) as u64]
+    }
+
+    /// 4 single precision lanes
+    pub fn f32s(self) -> [f32; 4] {
+        self.u32s().map(f32::from_bits)
+    }
+
+    /// 2 double precision lanes
+    pub fn f64s(self) -> [f64; 2] {
+        self.u64s().map(f64::from_bits)
+    }
+}
+
+/// ArmThreadState32 contains the general purpose registers
+/// of 32-bit ARM threads
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct ArmThreadState32 {
+    /// General Purpose Registers
+    pub r: [u32; 13],
+    /// Stack Pointer
+    pub sp: u32,
+    /// Link Register
+    pub lr: u32,
+    /// Program Counter
+    pub pc: u32,
+    /// Current Program Status Register
+    pub cpsr: u32,
+}
+
+impl ArmThreadState32 {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        let mut regs: [u32; 13] = [0; 13];
+        for reg in regs.iter_mut() {
+            *reg = r.u32();
+        }
+
+        Self {
+            r: regs,
+            sp: r.u32(),
+            lr: r.u32(),
+            pc: r.u32(),
+            cpsr: r.u32(),
+        }
+    }
+}
+
+impl fmt::Display for ArmThreadState32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "R0:  0x{:08x}   R1:  0x{:08x}   R2:  0x{:08x}   R3:  0x{:08x}\n\
+            R4:  0x{:08x}   R5:  0x{:08x}   R6:  0x{:08x}   R7:  0x{:08x}\n\
+            R8:  0x{:08x}   R9:  0x{:08x}   R10: 0x{:08x}   R11: 0x{:08x}\n\
+            R12: 0x{:08x}   SP:  0x{:08x}   LR:  0x{:08x}   PC:  0x{:08x}\n\
+            CPSR: 0x{:08x}\n",
+            self.r[0],
+            self.r[1],
+            self.r[2],
+            self.r[3],
+            self.r[4],
+            self.r[5],
+            self.r[6],
+            self.r[7],
+            self.r[8],
+            self.r[9],
+            self.r[10],
+            self.r[11],
+            self.r[12],
+            self.sp,
+            self.lr,
+            self.pc,
+            self.cpsr,
+        )
+    }
+}
+
+/// X86ThreadState32 contains the general purpose, segment
+/// and flag registers of i386 threads
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct X86ThreadState32 {
+    pub eax: u32,
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+    pub edi: u32,
+    pub esi: u32,
+    /// Frame Pointer
+    pub ebp: u32,
+    /// Stack Pointer
+    pub esp: u32,
+    pub ss: u32,
+    /// Flags Register
+    pub eflags: u32,
+    /// Instruction Pointer
+    pub eip: u32,
+    pub cs: u32,
+    pub ds: u32,
+    pub es: u32,
+    pub fs: u32,
+    pub gs: u32,
+}
+
+impl X86ThreadState32 {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        Self {
+            eax: r.u32(),
+            ebx: r.u32(),
+            ecx: r.u32(),
+            edx: r.u32(),
+            edi: r.u32(),
+            esi: r.u32(),
+            ebp: r.u32(),
+            esp: r.u32(),
+            ss: r.u32(),
+            eflags: r.u32(),
+            eip: r.u32(),
+            cs: r.u32(),
+            ds: r.u32(),
+            es: r.u32(),
+            fs: r.u32(),
+            gs: r.u32(),
+        }
+    }
+}
+
+impl fmt::Display for X86ThreadState32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "EAX: 0x{:08x}   EBX: 0x{:08x}   ECX: 0x{:08x}   EDX: 0x{:08x}\n\
+            EDI: 0x{:08x}   ESI: 0x{:08x}   EBP: 0x{:08x}   ESP: 0x{:08x}\n\
+            EIP: 0x{:08x}   EFLAGS: 0x{:08x}\n\
+            SS:  0x{:04x}   CS:  0x{:04x}   DS:  0x{:04x}   ES:  0x{:04x}   FS:  0x{:04x}   GS:  0x{:04x}\n",
+            self.eax,
+            self.ebx,
+            self.ecx,
+            self.edx,
+            self.edi,
+            self.esi,
+            self.ebp,
+            self.esp,
+            self.eip,
+            self.eflags,
+            self.ss,
+            self.cs,
+            self.ds,
+            self.es,
+            self.fs,
+            self.gs,
+        )
+    }
+}
+
+/// X86ThreadState64 contains the general purpose, segment
+/// and flag registers of x86_64 threads
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct X86ThreadState64 {
+    pub rax: u64,
+    pub rbx: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rdi: u64,
+    pub rsi: u64,
+    /// Frame Pointer
+    pub rbp: u64,
+    /// Stack Pointer
+    pub rsp: u64,
+    pub r8: u64,
+    pub r9: u64,
+    pub r10: u64,
+    pub r11: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
+    /// Instruction Pointer
+    pub rip: u64,
+    /// Flags Register
+    pub rflags: u64,
+    pub cs: u64,
+    pub fs: u64,
+    pub gs: u64,
+}
+
+impl X86ThreadState64 {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        Self {
+            rax: r.u64(),
+            rbx: r.u64(),
+            rcx: r.u64(),
+            rdx: r.u64(),
+            rdi: r.u64(),
+            rsi: r.u64(),
+            rbp: r.u64(),
+            rsp: r.u64(),
+            r8: r.u64(),
+            r9: r.u64(),
+            r10: r.u64(),
+            r11: r.u64(),
+            r12: r.u64(),
+            r13: r.u64(),
+            r14: r.u64(),
+            r15: r.u64(),
+            rip: r.u64(),
+            rflags: r.u64(),
+            cs: r.u64(),
+            fs: r.u64(),
+            gs: r.u64(),
+        }
+    }
+}
+
+impl fmt::Display for X86ThreadState64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RAX: 0x{:016x}   RBX: 0x{:016x}   RCX: 0x{:016x}   RDX: 0x{:016x}\n\
+            RDI: 0x{:016x}   RSI: 0x{:016x}   RBP: 0x{:016x}   RSP: 0x{:016x}\n\
+            R8:  0x{:016x}   R9:  0x{:016x}   R10: 0x{:016x}   R11: 0x{:016x}\n\
+            R12: 0x{:016x}   R13: 0x{:016x}   R14: 0x{:016x}   R15: 0x{:016x}\n\
+            RIP: 0x{:016x}   RFLAGS: 0x{:016x}\n\
+            CS:  0x{:04x}   FS:  0x{:04x}   GS:  0x{:04x}\n",
+            self.rax,
+            self.rbx,
+            self.rcx,
+            self.rdx,
+            self.rdi,
+            self.rsi,
+            self.rbp,
+            self.rsp,
+            self.r8,
+            self.r9,
+            self.r10,
+            self.r11,
+            self.r12,
+            self.r13,
+            self.r14,
+            self.r15,
+            self.rip,
+            self.rflags,
+            self.cs,
+            self.fs,
+            self.gs,
+        )
+    }
+}
+
+/// X86FloatState64 contains the x87, MMX and SSE registers
+/// of x86_64 threads as saved by `fxsave`
+#[derive(Copy, Clone, Debug)]
+pub struct X86FloatState64 {
+    /// x87 Control Word
+    pub fcw: u16,
+    /// x87 Status Word
+    pub fsw: u16,
+    /// Abridged x87 Tag Word
+    pub ftw: u8,
+    /// Opcode of the last x87 instruction
+    pub fop: u16,
+    /// Instruction Pointer of the last x87 instruction
+    pub ip: u32,
+    pub cs: u16,
+    /// Data Pointer of the last x87 instruction
+    pub dp: u32,
+    pub ds: u16,
+    /// SSE Control and Status Register
+    pub mxcsr: u32,
+    /// Bits of `mxcsr` which are supported
+    pub mxcsrmask: u32,
+    /// x87 and MMX registers with 80 bits each
+    pub stmm: [[u8; 10]; 8],
+    /// SSE registers
+    pub xmm: [u128; 16],
+}
+
+impl X86FloatState64 {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        r.skip(8);
+        let fcw = r.u16();
+        let fsw = r.u16();
+        let ftw = r.u8();
+        r.skip(1);
+        let fop = r.u16();
+        let ip = r.u32();
+        let cs = r.u16();
+        r.skip(2);
+        let dp = r.u32();
+        let ds = r.u16();
+        r.skip(2);
+        let mxcsr = r.u32();
+        let mxcsrmask = r.u32();
+
+        let mut stmm = [[0; 10]; 8];
+        for reg in stmm.iter_mut() {
+            *reg = r.bytes();
+            r.skip(6);
+        }
+        let mut xmm = [0; 16];
+        for reg in xmm.iter_mut() {
+            *reg = r.u128();
+        }
+        // Reserved space up to the end of the state
+        r.skip(96 + 4);
+
+        Self {
+            fcw,
+            fsw,
+            ftw,
+            fop,
+            ip,
+            cs,
+            dp,
+            ds,
+            mxcsr,
+            mxcsrmask,
+            stmm,
+            xmm,
+        }
+    }
+}
+
+impl fmt::Display for X86FloatState64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "FCW: 0x{:04x}   FSW: 0x{:04x}   FTW: 0x{:02x}   FOP: 0x{:04x}",
+            self.fcw, self.fsw, self.ftw, self.fop
+        )?;
+        writeln!(
+            f,
+            "IP:  0x{:08x}   CS:  0x{:04x}   DP:  0x{:08x}   DS:  0x{:04x}",
+            self.ip, self.cs, self.dp, self.ds
+        )?;
+        writeln!(
+            f,
+            "MXCSR: 0x{:08x}   MXCSRMASK: 0x{:08x}",
+            self.mxcsr, self.mxcsrmask
+        )?;
+        for (i, reg) in self.stmm.iter().enumerate() {
+            // Registers are stored little-endian, print the
+            // most significant byte first
+            write!(f, "{:<7}0x", format!("ST{}:", i))?;
+            for byte in reg.iter().rev() {
+                write!(f, "{:02x}", byte)?;
+            }
+            writeln!(f)?;
+        }
+        for (i, reg) in self.xmm.iter().enumerate() {
+            writeln!(f, "{:<7}0x{:032x}", format!("XMM{}:", i), reg)?;
+        }
+        Ok(())
+    }
+}
+
+/// X86AvxState64 extends `X86FloatState64` with the upper
+/// halves of the AVX registers
+#[derive(Copy, Clone, Debug)]
+pub struct X86AvxState64 {
+    /// x87, MMX and SSE registers
+    pub float: X86FloatState64,
+    /// Upper 128 bits of the AVX registers, the lower ones
+    /// are the SSE registers
+    pub ymmh: [u128; 16],
+}
+
+impl X86AvxState64 {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        let float = X86FloatState64::read(r);
+        r.skip(64);
+        let mut ymmh = [0; 16];
+        for reg in ymmh.iter_mut() {
+            *reg = r.u128();
+        }
+        Self { float, ymmh }
+    }
+
+    /// AVX register `n` as lower and upper 128 bits
+    pub fn ymm(&self, n: usize) -> (u128, u128) {
+        (self.float.xmm[n], self.ymmh[n])
+    }
+}
+
+impl fmt::Display for X86AvxState64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.float)?;
+        for i in 0..self.ymmh.len() {
+            let (low, high) = self.ymm(i);
+            writeln!(f, "{:<7}0x{:032x}{:032x}", format!("YMM{}:", i), high, low)?;
+        }
+        Ok(())
+    }
+}
+
+/// X86ExceptionState64 describes the last exception taken
+/// by an x86_64 thread
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct X86ExceptionState64 {
+    /// Trap number, like 14 for page faults
+    pub trapno: u16,
+    /// CPU the exception was taken on
+    pub cpu: u16,
+    /// Error code pushed by the CPU
+    pub err: u32,
+    /// Faulting address of page faults
+    pub faultvaddr: u64,
+}
+
+impl X86ExceptionState64 {
+    pub(crate) fn read(r: &mut Reader) -> Self {
+        Self {
+            trapno: r.u16(),
+            cpu: r.u16(),
+            err: r.u32(),
+            faultvaddr: r.u64(),
+        }
+    }
+}
+
+impl fmt::Display for X86ExceptionState64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "TRAPNO: {}   CPU: {}   ERR: 0x{:08x}   FAULTVADDR: 0x{:016x}",
+            self.trapno, self.cpu, self.err, self.faultvaddr
+        )
+    }
+}
@@ -0,0 +1,385 @@
+use crate::cpu::CpuType;
+use crate::error::MachoError;
+use crate::load_command::{LoadCommand, SegmentCommand64, ThreadCommand};
+use crate::mach_header::MachHeader;
+use crate::thread_state::FlavorState;
+use std::fmt;
+
+/// Decides how problems found while loading a core dump are handled
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ValidationMode {
+    /// Reject the core dump if any error is found
+    #[default]
+    Strict,
+    /// Load as much as possible and attach all problems to `Macho`
+    Lenient,
+}
+
+/// Severity of a diagnostic
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Unusual, but the core dump can still be used
+    Warning,
+    /// Structure is broken, parts of the core dump may be missing
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Problems which can be found by the validation pass
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// `ncmds` commands do not add up to `sizeofcmds` bytes
+    CommandCountMismatch {
+        ncmds: u32,
+        sizeofcmds: u32,
+        actual: u64,
+    },
+    /// `cmdsize` is not a multiple of 8 (4 in 32-bit files)
+    MisalignedCommandSize { cmdsize: u32, alignment: u32 },
+    /// Command does not fit into `sizeofcmds` or the file
+    CommandOutOfBounds { cmdsize: u32 },
+    /// Thread state flavor is not understood, the state was
+    /// kept as raw words
+    UnsupportedThreadFlavor { flavor: u32 },
+    /// State of a thread flavor does not fit into its command
+    ThreadStateOutOfBounds { flavor: u32, count: u32 },
+    /// Known thread flavor holds fewer words than its state
+    /// needs, the state was kept as raw words
+    ThreadStateTruncated {
+        flavor: u32,
+        expected: u32,
+        count: u32,
+    },
+    /// File range of a segment lies past the end of the file
+    SegmentOutOfBounds { fileoff: u64, filesize: u64 },
+    /// Payload of a note lies past the end of the file
+    NoteOutOfBounds { noteoff: u64, size: u64 },
+    /// Virtual memory range overlaps the segment at `other`
+    OverlappingVmRange { vmaddr: u64, vmsize: u64, other: usize },
+    /// File range overlaps the segment at `other`
+    OverlappingFileRange {
+        fileoff: u64,
+        filesize: u64,
+        other: usize,
+    },
+    /// Segment stores more bytes than it maps
+    FileSizeExceedsVmSize { filesize: u64, vmsize: u64 },
+    /// Initial and maximum permissions differ
+    ProtectionMismatch { initprot: i32, maxprot: i32 },
+}
+
+impl DiagnosticKind {
+    /// Severity of this kind of problem
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticKind::MisalignedCommandSize { .. }
+            | DiagnosticKind::UnsupportedThreadFlavor { .. }
+            | DiagnosticKind::OverlappingFileRange { .. }
+            | DiagnosticKind::ProtectionMismatch { .. } => Severity::Warning,
+            DiagnosticKind::CommandCountMismatch { .. }
+            | DiagnosticKind::CommandOutOfBounds { .. }
+            | DiagnosticKind::ThreadStateOutOfBounds { .. }
+            | DiagnosticKind::ThreadStateTruncated { .. }
+            | DiagnosticKind::SegmentOutOfBounds { .. }
+            | DiagnosticKind::NoteOutOfBounds { .. }
+            | DiagnosticKind::OverlappingVmRange { .. }
+            | DiagnosticKind::FileSizeExceedsVmSize { .. } => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticKind::CommandCountMismatch {
+                ncmds,
+                sizeofcmds,
+                actual,
+            } => write!(
+                f,
+                "{} commands use {} bytes, but sizeofcmds is {}",
+                ncmds, actual, sizeofcmds
+            ),
+            DiagnosticKind::MisalignedCommandSize { cmdsize, alignment } => {
+                write!(f, "cmdsize {} is not a multiple of {}", cmdsize, alignment)
+            }
+            DiagnosticKind::CommandOutOfBounds { cmdsize } => {
+                write!(f, "command with size {} is out of bounds", cmdsize)
+            }
+            DiagnosticKind::UnsupportedThreadFlavor { flavor } => {
+                write!(f, "unsupported thread flavor {}", flavor)
+            }
+            DiagnosticKind::ThreadStateOutOfBounds { flavor, count } => write!(
+                f,
+                "thread flavor {} with {} words does not fit into its command",
+                flavor, count
+            ),
+            DiagnosticKind::ThreadStateTruncated {
+                flavor,
+                expected,
+                count,
+            } => write!(
+                f,
+                "thread flavor {} has {} words, but its state needs {}",
+                flavor, count, expected
+            ),
+            DiagnosticKind::SegmentOutOfBounds { fileoff, filesize } => write!(
+                f,
+                "file range 0x{:x}..0x{:x} is past end of file",
+                fileoff,
+                fileoff.saturating_add(*filesize)
+            ),
+            DiagnosticKind::NoteOutOfBounds { noteoff, size } => write!(
+                f,
+                "note payload 0x{:x}..0x{:x} is past end of file",
+                noteoff,
+                noteoff.saturating_add(*size)
+            ),
+            DiagnosticKind::OverlappingVmRange {
+                vmaddr,
+                vmsize,
+                other,
+            } => write!(
+                f,
+                "vm range 0x{:x}..0x{:x} overlaps segment at offset 0x{:x}",
+                vmaddr,
+                vmaddr.saturating_add(*vmsize),
+                other
+            ),
+            DiagnosticKind::OverlappingFileRange {
+                fileoff,
+                filesize,
+                other,
+            } => write!(
+                f,
+                "file range 0x{:x}..0x{:x} overlaps segment at offset 0x{:x}",
+                fileoff,
+                fileoff.saturating_add(*filesize),
+                other
+            ),
+            DiagnosticKind::FileSizeExceedsVmSize { filesize, vmsize } => write!(
+                f,
+                "filesize 0x{:x} is larger than vmsize 0x{:x}",
+                filesize, vmsize
+            ),
+            DiagnosticKind::ProtectionMismatch { initprot, maxprot } => write!(
+                f,
+                "initprot 0x{:x} differs from maxprot 0x{:x}",
+                initprot, maxprot
+            ),
+        }
+    }
+}
+
+/// Single problem found in a core dump
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// How bad the problem is
+    pub severity: Severity,
+    /// Byte offset of the offending structure in the file
+    pub offset: usize,
+    /// What is wrong
+    pub kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    pub fn new(offset: usize, kind: DiagnosticKind) -> Self {
+        Self {
+            severity: kind.severity(),
+            offset,
+            kind,
+        }
+    }
+
+    /// Converts a parse error the loader can recover from
+    /// into a diagnostic
+    pub(crate) fn from_error(err: &MachoError) -> Option<Self> {
+        let kind = match *err {
+            MachoError::LoadCommandOutOfBounds { cmdsize, .. } => {
+                DiagnosticKind::CommandOutOfBounds { cmdsize }
+            }
+            MachoError::SegmentOutOfBounds {
+                fileoff, filesize, ..
+            } => DiagnosticKind::SegmentOutOfBounds { fileoff, filesize },
+            MachoError::NoteOutOfBounds { noteoff, size, .. } => {
+                DiagnosticKind::NoteOutOfBounds { noteoff, size }
+            }
+            MachoError::UnsupportedThreadFlavor { flavor, .. } => {
+                DiagnosticKind::UnsupportedThreadFlavor { flavor }
+            }
+            MachoError::ThreadStateOutOfBounds { flavor, count, .. } => {
+                DiagnosticKind::ThreadStateOutOfBounds { flavor, count }
+            }
+            _ => return None,
+        };
+        Some(Self::new(err.offset(), kind))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset 0x{:x}: {}", self.severity, self.offset, self.kind)
+    }
+}
+
+/// Checks command sizes against each other and against `sizeofcmds`.
+/// `commands` holds every parsed command with its file offset.
+pub(crate) fn check_commands(
+    header: &MachHeader,
+    commands: &[(usize, LoadCommand)],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let cmds_end = header.size() + header.sizeofcmds as usize;
+    let alignment = if header.is_64() { 8 } else { 4 };
+
+    for (offset, lc) in commands {
+        if lc.cmdsize % alignment != 0 {
+            diagnostics.push(Diagnostic::new(
+                *offset,
+                DiagnosticKind::MisalignedCommandSize {
+                    cmdsize: lc.cmdsize,
+                    alignment,
+                },
+            ));
+        }
+        if offset + lc.cmdsize as usize > cmds_end {
+            diagnostics.push(Diagnostic::new(
+                *offset,
+                DiagnosticKind::CommandOutOfBounds {
+                    cmdsize: lc.cmdsize,
+                },
+            ));
+        }
+    }
+
+    let actual: u64 = commands.iter().map(|(_, lc)| lc.cmdsize as u64).sum();
+    if commands.len() == header.ncmds as usize && actual != header.sizeofcmds as u64 {
+        diagnostics.push(Diagnostic::new(
+            0,
+            DiagnosticKind::CommandCountMismatch {
+                ncmds: header.ncmds,
+                sizeofcmds: header.sizeofcmds,
+                actual,
+            },
+        ));
+    }
+}
+
+/// Reports known thread flavors which are too short to decode.
+/// Unknown flavors are reported while loading. `threads` holds
+/// every thread command with its file offset.
+pub(crate) fn check_threads(
+    cputype: CpuType,
+    threads: &[(usize, &ThreadCommand)],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (offset, tc) in threads {
+        for (pos, flavor) in tc.flavor_offsets() {
+            let decoder = FlavorState::decoder(cputype, flavor.flavor);
+            if let (FlavorState::Raw(_), Some((size, _))) = (&flavor.state, decoder) {
+                diagnostics.push(Diagnostic::new(
+                    offset + pos,
+                    DiagnosticKind::ThreadStateTruncated {
+                        flavor: flavor.flavor,
+                        expected: size.div_ceil(4) as u32,
+                        count: flavor.count,
+                    },
+                ));
+            }
+        }
+    }
+}
+
+/// Checks segment sizes, permissions and overlaps.
+/// `segments` holds every segment command with its file offset.
+pub(crate) fn check_segments(
+    segments: &[(usize, SegmentCommand64)],
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (offset, sc) in segments {
+        if sc.filesize > sc.vmsize {
+            diagnostics.push(Diagnostic::new(
+                *offset,
+                DiagnosticKind::FileSizeExceedsVmSize {
+                    filesize: sc.filesize,
+                    vmsize: sc.vmsize,
+                },
+            ));
+        }
+        if sc.initprot != sc.maxprot {
+            diagnostics.push(Diagnostic::new(
+                *offset,
+                DiagnosticKind::ProtectionMismatch {
+                    initprot: sc.initprot,
+                    maxprot: sc.maxprot,
+                },
+            ));
+        }
+    }
+
+    let vm_ranges: Vec<(u64, u64)> = segments
+        .iter()
+        .map(|(_, sc)| (sc.vmaddr, sc.vmsize))
+        .collect();
+    for (i, j) in overlaps(&vm_ranges) {
+        let (offset, sc) = &segments[i];
+        diagnostics.push(Diagnostic::new(
+            *offset,
+            DiagnosticKind::OverlappingVmRange {
+                vmaddr: sc.vmaddr,
+                vmsize: sc.vmsize,
+                other: segments[j].0,
+            },
+        ));
+    }
+
+    let file_ranges: Vec<(u64, u64)> = segments
+        .iter()
+        .map(|(_, sc)| (sc.fileoff, sc.filesize))
+        .collect();
+    for (i, j) in overlaps(&file_ranges) {
+        let (offset, sc) = &segments[i];
+        diagnostics.push(Diagnostic::new(
+            *offset,
+            DiagnosticKind::OverlappingFileRange {
+                fileoff: sc.fileoff,
+                filesize: sc.filesize,
+                other: segments[j].0,
+            },
+        ));
+    }
+}
+
+/// Takes `(start, size)` ranges and returns `(i, j)` for every range `i`
+/// which overlaps an earlier starting range `j`. Empty ranges never overlap.
+fn overlaps(ranges: &[(u64, u64)]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..ranges.len()).filter(|&i| ranges[i].1 != 0).collect();
+    order.sort_by_key(|&i| (ranges[i].0, i));
+
+    let mut found = Vec::new();
+    // Index and end of the range reaching furthest so far
+    let mut furthest: Option<(usize, u64)> = None;
+    for i in order {
+        let (start, size) = ranges[i];
+        let end = start.saturating_add(size);
+        if let Some((j, furthest_end)) = furthest {
+            if start < furthest_end {
+                found.push((i, j));
+            }
+            if end <= furthest_end {
+                continue;
+            }
+        }
+        furthest = Some((i, end));
+    }
+    found.sort_unstable();
+    found
+}
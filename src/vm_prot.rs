@@ -0,0 +1,68 @@
+use std::fmt;
+use std::ops::{BitAnd, BitOr};
+
+/// Virtual memory protection of a segment
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct VmProt(pub i32);
+
+impl VmProt {
+    pub const NONE: VmProt = VmProt(0x0);
+    pub const READ: VmProt = VmProt(0x1);
+    pub const WRITE: VmProt = VmProt(0x2);
+    pub const EXECUTE: VmProt = VmProt(0x4);
+    /// Only used when changing protections, keeps the current one
+    pub const NO_CHANGE: VmProt = VmProt(0x8);
+    /// Copy-on-write, only used when changing protections
+    pub const COPY: VmProt = VmProt(0x10);
+
+    pub const READ_WRITE: VmProt = VmProt(Self::READ.0 | Self::WRITE.0);
+    pub const READ_EXECUTE: VmProt = VmProt(Self::READ.0 | Self::EXECUTE.0);
+    pub const ALL: VmProt = VmProt(Self::READ.0 | Self::WRITE.0 | Self::EXECUTE.0);
+
+    /// Returns true if all bits of `other` are set
+    pub fn contains(self, other: VmProt) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_readable(self) -> bool {
+        self.contains(Self::READ)
+    }
+
+    pub fn is_writable(self) -> bool {
+        self.contains(Self::WRITE)
+    }
+
+    pub fn is_executable(self) -> bool {
+        self.contains(Self::EXECUTE)
+    }
+}
+
+impl BitOr for VmProt {
+    type Output = VmProt;
+
+    fn bitor(self, rhs: VmProt) -> VmProt {
+        VmProt(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for VmProt {
+    type Output = VmProt;
+
+    fn bitand(self, rhs: VmProt) -> VmProt {
+        VmProt(self.0 & rhs.0)
+    }
+}
+
+/// Formats the protection like `r-x`
+impl fmt::Display for VmProt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |prot: VmProt, c: char| if self.contains(prot) { c } else { '-' };
+        write!(
+            f,
+            "{}{}{}",
+            flag(Self::READ, 'r'),
+            flag(Self::WRITE, 'w'),
+            flag(Self::EXECUTE, 'x')
+        )
+    }
+}
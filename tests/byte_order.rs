@@ -0,0 +1,92 @@
+mod common;
+
+use common::Writer;
+use mach_dump::cpu::{CPU_TYPE_ARM64, MH_CIGAM_64, MH_MAGIC_64};
+use mach_dump::endian::Endian;
+use mach_dump::macho::Macho;
+use mach_dump::thread::RegisterContext;
+
+/// ARM64 core with one segment and one thread, every field
+/// written in the byte order given by `big`
+fn core(big: bool) -> Vec<u8> {
+    let mut w = Writer::new(big);
+    w.u32(MH_MAGIC_64)
+        .u32(CPU_TYPE_ARM64.0)
+        .u32(0)
+        .u32(4)
+        .u32(2)
+        .u32(72 + 288)
+        .u32(0)
+        .u32(0);
+
+    // LC_SEGMENT_64
+    w.u32(0x19).u32(72).bytes(&[0; 16]);
+    w.u64(0x1_0000_0000).u64(0x4000).u64(0x1000).u64(0x10);
+    w.u32(3).u32(3).u32(0).u32(0);
+
+    // LC_THREAD with ARM_THREAD_STATE64
+    w.u32(4).u32(288).u32(6).u32(68);
+    for x in 0..29 {
+        w.u64(x);
+    }
+    w.u64(0x16f000100)
+        .u64(0x100003f00)
+        .u64(0x16f000000)
+        .u64(0x100004000)
+        .u32(0x6000_0000)
+        .u32(0);
+
+    w.pad(0x1000);
+    w.u32(0x1122_3344).u64(0x5566_7788_99aa_bbcc);
+    w.pad(0x1010);
+    w.buf
+}
+
+#[test]
+fn big_endian_core() {
+    let macho = Macho::from_stream(&core(true)[..]).unwrap();
+    assert_eq!(macho.header.magic, MH_CIGAM_64);
+    assert_eq!(macho.header.endian(), Endian::Big);
+    assert_eq!(macho.header.cputype, CPU_TYPE_ARM64);
+    assert_eq!(macho.header.ncmds, 2);
+    assert_eq!(macho.header.sizeofcmds, 72 + 288);
+
+    let segment = &macho.segments[0];
+    assert_eq!(segment.vmaddr, 0x1_0000_0000);
+    assert_eq!(segment.vmsize, 0x4000);
+    assert_eq!(segment.fileoff, 0x1000);
+    assert_eq!(segment.filesize, 0x10);
+
+    // Memory is stored in the byte order of the core dump
+    assert_eq!(macho.read_u32(0x1_0000_0000).unwrap(), 0x1122_3344);
+    assert_eq!(
+        macho.read_u64(0x1_0000_0004).unwrap(),
+        0x5566_7788_99aa_bbcc
+    );
+
+    let threads = macho.get_threads();
+    let context = threads[0].context.unwrap();
+    assert_eq!(context.pc(), 0x100004000);
+    assert_eq!(context.sp(), 0x16f000000);
+    assert_eq!(context.register("x28"), Some(28));
+}
+
+#[test]
+fn byte_orders_agree() {
+    let big = Macho::from_stream(&core(true)[..]).unwrap();
+    let little = Macho::from_stream(&core(false)[..]).unwrap();
+    assert_eq!(little.header.magic, MH_MAGIC_64);
+    assert_eq!(little.header.endian(), Endian::Little);
+    assert_eq!(
+        format!("{:?}", big.segments),
+        format!("{:?}", little.segments)
+    );
+    assert_eq!(
+        big.get_threads()[0].context.unwrap().registers(),
+        little.get_threads()[0].context.unwrap().registers()
+    );
+    assert_eq!(
+        big.read_u64(0x1_0000_0004).unwrap(),
+        little.read_u64(0x1_0000_0004).unwrap()
+    );
+}
@@ -0,0 +1,133 @@
+//! Builders for synthetic core dumps
+
+#![allow(dead_code)]
+
+const MH_MAGIC_64: u32 = 0xfeedfacf;
+const CPU_TYPE_ARM64: u32 = 0x0100000c;
+const MH_CORE: u32 = 4;
+const LC_SEGMENT_64: u32 = 0x19;
+const LC_NOTE: u32 = 0x31;
+const LC_THREAD: u32 = 4;
+
+/// 64-bit ARM64 core header followed by `commands`
+pub fn core(ncmds: u32, sizeofcmds: u32, commands: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for field in [
+        MH_MAGIC_64,
+        CPU_TYPE_ARM64,
+        0,
+        MH_CORE,
+        ncmds,
+        sizeofcmds,
+        0,
+        0,
+    ] {
+        buf.extend_from_slice(&field.to_le_bytes());
+    }
+    buf.extend_from_slice(commands);
+    buf
+}
+
+/// `segment_command_64` mapping `vmaddr` from `fileoff`
+pub fn segment(vmaddr: u64, fileoff: u64, filesize: u64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&LC_SEGMENT_64.to_le_bytes());
+    buf.extend_from_slice(&72u32.to_le_bytes());
+    buf.extend_from_slice(&[0; 16]);
+    for field in [vmaddr, 0x1000, fileoff, filesize] {
+        buf.extend_from_slice(&field.to_le_bytes());
+    }
+    for field in [3u32, 3, 0, 0] {
+        buf.extend_from_slice(&field.to_le_bytes());
+    }
+    buf
+}
+
+/// `note_command` of `owner` with the payload at `offset`
+pub fn note(owner: &str, offset: u64, size: u64) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&LC_NOTE.to_le_bytes());
+    buf.extend_from_slice(&40u32.to_le_bytes());
+    let mut data_owner = [0; 16];
+    data_owner[..owner.len()].copy_from_slice(owner.as_bytes());
+    buf.extend_from_slice(&data_owner);
+    buf.extend_from_slice(&offset.to_le_bytes());
+    buf.extend_from_slice(&size.to_le_bytes());
+    buf
+}
+
+/// `thread_command` with `(flavor, state)` entries
+pub fn thread(flavors: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (flavor, state) in flavors {
+        body.extend_from_slice(&flavor.to_le_bytes());
+        body.extend_from_slice(&(state.len() as u32 / 4).to_le_bytes());
+        body.extend_from_slice(state);
+    }
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&LC_THREAD.to_le_bytes());
+    buf.extend_from_slice(&(8 + body.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&body);
+    buf
+}
+
+/// Core with `segments`, padded with zeros to `len` bytes
+pub fn core_with_segments(segments: &[Vec<u8>], len: usize) -> Vec<u8> {
+    let commands = segments.concat();
+    let mut buf = core(segments.len() as u32, commands.len() as u32, &commands);
+    buf.resize(len, 0);
+    buf
+}
+
+/// Appends fields in little or big endian byte order
+pub struct Writer {
+    pub buf: Vec<u8>,
+    big: bool,
+}
+
+impl Writer {
+    pub fn new(big: bool) -> Self {
+        Self {
+            buf: Vec::new(),
+            big,
+        }
+    }
+
+    pub fn u16(&mut self, value: u16) -> &mut Self {
+        let bytes = if self.big {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        };
+        self.bytes(&bytes)
+    }
+
+    pub fn u32(&mut self, value: u32) -> &mut Self {
+        let bytes = if self.big {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        };
+        self.bytes(&bytes)
+    }
+
+    pub fn u64(&mut self, value: u64) -> &mut Self {
+        let bytes = if self.big {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        };
+        self.bytes(&bytes)
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(bytes);
+        self
+    }
+
+    /// Pads with zeros up to `len` bytes
+    pub fn pad(&mut self, len: usize) -> &mut Self {
+        self.buf.resize(len, 0);
+        self
+    }
+}
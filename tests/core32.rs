@@ -0,0 +1,99 @@
+mod common;
+
+use common::Writer;
+use mach_dump::address_space::PointerWidth;
+use mach_dump::cpu::{CpuType, CPU_TYPE_ARM, CPU_TYPE_X86, MH_MAGIC};
+use mach_dump::macho::Macho;
+use mach_dump::thread::RegisterContext;
+use mach_dump::thread_state::ThreadState;
+
+/// 32-bit core for `cputype` with one `LC_SEGMENT` and one thread
+/// holding `words` as the state of `flavor`
+fn core(cputype: CpuType, flavor: u32, words: &[u32]) -> Vec<u8> {
+    let thread_size = 16 + words.len() as u32 * 4;
+    let mut w = Writer::new(false);
+    // 28 byte header without the reserved field
+    w.u32(MH_MAGIC)
+        .u32(cputype.0)
+        .u32(0)
+        .u32(4)
+        .u32(2)
+        .u32(56 + thread_size)
+        .u32(0);
+
+    // LC_SEGMENT
+    w.u32(1).u32(56).bytes(&[0; 16]);
+    w.u32(0x4000).u32(0x2000).u32(0x1000).u32(0x10);
+    w.u32(3).u32(3).u32(0).u32(0);
+
+    w.u32(4)
+        .u32(thread_size)
+        .u32(flavor)
+        .u32(words.len() as u32);
+    for &word in words {
+        w.u32(word);
+    }
+
+    w.pad(0x1000);
+    w.u32(0x8000).u32(0xdead_beef);
+    w.pad(0x1010);
+    w.buf
+}
+
+#[test]
+fn arm_core() {
+    let mut words: Vec<u32> = (0..13).collect();
+    words.extend_from_slice(&[0x7000, 0x4010, 0x4020, 0x10]);
+    let macho = Macho::from_stream(&core(CPU_TYPE_ARM, 1, &words)[..]).unwrap();
+    assert!(!macho.header.is_64());
+    assert_eq!(macho.header.size(), 28);
+    assert_eq!(macho.pointer_width, PointerWidth::Bits32);
+
+    let segment = &macho.segments[0];
+    assert_eq!(segment.vmaddr, 0x4000);
+    assert_eq!(segment.vmsize, 0x2000);
+    assert_eq!(segment.fileoff, 0x1000);
+    assert_eq!(segment.filesize, 0x10);
+    assert_eq!(macho.read_ptr(0x4000).unwrap(), 0x8000);
+    assert_eq!(macho.read_u32(0x4004).unwrap(), 0xdead_beef);
+
+    let threads = macho.get_threads();
+    assert_eq!(threads.len(), 1);
+    let state = match threads[0].context {
+        Some(ThreadState::Arm(state)) => state,
+        context => panic!("unexpected {:?}", context),
+    };
+    assert_eq!(state.r[12], 12);
+    assert_eq!(state.sp, 0x7000);
+    assert_eq!(state.lr, 0x4010);
+    assert_eq!(state.pc, 0x4020);
+    assert_eq!(state.cpsr, 0x10);
+    assert_eq!(state.fp(), 7);
+    assert_eq!(state.return_address(), Some(0x4010));
+    assert_eq!(state.dwarf_register(15), Some(0x4020));
+}
+
+#[test]
+fn i386_core() {
+    // eax, ebx, ecx, edx, edi, esi, ebp, esp, ss, eflags, eip,
+    // cs, ds, es, fs, gs
+    let words = [
+        1, 2, 3, 4, 5, 6, 0x7100, 0x7000, 0x23, 0x202, 0x4020, 0x1b, 0x23, 0x23, 0, 0x0f,
+    ];
+    let macho = Macho::from_stream(&core(CPU_TYPE_X86, 1, &words)[..]).unwrap();
+    assert_eq!(macho.segments[0].vmaddr, 0x4000);
+
+    let threads = macho.get_threads();
+    let state = match threads[0].context {
+        Some(ThreadState::X86(state)) => state,
+        context => panic!("unexpected {:?}", context),
+    };
+    assert_eq!(state.eax, 1);
+    assert_eq!(state.esi, 6);
+    assert_eq!(state.eflags, 0x202);
+    assert_eq!(state.gs, 0x0f);
+    assert_eq!(state.pc(), 0x4020);
+    assert_eq!(state.sp(), 0x7000);
+    assert_eq!(state.fp(), 0x7100);
+    assert_eq!(state.register("ecx"), Some(3));
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{core_with_segments, segment};
+use mach_dump::core_style::CoreStyle;
+use mach_dump::error::MachoError;
+use mach_dump::macho::Macho;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+/// Core with one fully stored segment at 0x10000 and a second one
+/// at 0x20000 storing `filesize` bytes
+fn core(filesize: u64) -> Macho {
+    let mut buf = core_with_segments(
+        &[
+            segment(0x10000, 0x1000, 0x1000),
+            segment(0x20000, 0x2000, filesize),
+        ],
+        0x2000 + filesize as usize,
+    );
+    buf[0x1000..].iter_mut().for_each(|byte| *byte = b'a');
+    Macho::from_reader(Cursor::new(buf)).unwrap()
+}
+
+#[test]
+fn short_tail_in_full_core() {
+    let macho = core(0x800);
+    assert_eq!(macho.style, CoreStyle::Full);
+    assert_eq!(macho.read(0x207fe, 4).unwrap(), b"aa\0\0");
+    assert_eq!(macho.read_cstring(0x20000).unwrap().len(), 0x800);
+    assert_eq!(macho.read_cstring(0x20900).unwrap(), "");
+
+    let mut cursor = macho.cursor();
+    cursor.seek(SeekFrom::Start(0x20ffe)).unwrap();
+    let mut buf = [1; 2];
+    cursor.read_exact(&mut buf).unwrap();
+    assert_eq!(buf, [0, 0]);
+}
+
+#[test]
+fn segment_left_out() {
+    let mut macho = core(0);
+    assert_eq!(macho.style, CoreStyle::ModifiedMemory);
+    assert!(matches!(
+        macho.read(0x20000, 4),
+        Err(MachoError::NotCaptured { address: 0x20000 })
+    ));
+
+    macho.style = CoreStyle::Full;
+    assert_eq!(macho.read(0x20000, 4).unwrap(), [0; 4]);
+}
+
+#[test]
+fn short_tail_in_modified_memory_core() {
+    // Only the tail of the segment is left out, which is detected
+    // as a full core dump until the caller says otherwise
+    let mut macho = core(0x800);
+    assert_eq!(macho.style, CoreStyle::Full);
+
+    macho.style = CoreStyle::ModifiedMemory;
+    assert_eq!(macho.read(0x207fc, 4).unwrap(), b"aaaa");
+    assert!(matches!(
+        macho.read(0x207fe, 4),
+        Err(MachoError::NotCaptured { address: 0x20800 })
+    ));
+    assert!(matches!(
+        macho.read_cstring(0x20000),
+        Err(MachoError::NotCaptured { address: 0x20800 })
+    ));
+}
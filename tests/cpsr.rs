@@ -0,0 +1,82 @@
+mod common;
+
+use common::{core, thread};
+use mach_dump::cpsr::Cpsr;
+use mach_dump::macho::Macho;
+use mach_dump::thread::RegisterContext;
+use mach_dump::thread_state::ThreadState;
+
+#[test]
+fn condition_flags() {
+    let cpsr = Cpsr(0x6000_0000);
+    assert!(!cpsr.n());
+    assert!(cpsr.z());
+    assert!(cpsr.c());
+    assert!(!cpsr.v());
+    assert_eq!(Cpsr(0x9000_0000).to_string(), "NzcV EL0t daif");
+}
+
+#[test]
+fn aarch64_modes() {
+    // EL0 using SP_EL0, user space threads
+    let cpsr = Cpsr(0);
+    assert!(!cpsr.is_aarch32());
+    assert_eq!(cpsr.exception_level(), Some(0));
+    assert_eq!(cpsr.sp_elx(), Some(false));
+    assert_eq!(cpsr.aarch32_mode(), None);
+
+    // EL1h, kernel threads
+    let cpsr = Cpsr(0b0101);
+    assert_eq!(cpsr.exception_level(), Some(1));
+    assert_eq!(cpsr.sp_elx(), Some(true));
+    assert_eq!(cpsr.to_string(), "nzcv EL1h daif");
+}
+
+#[test]
+fn aarch32_mode() {
+    // User mode with the Z flag
+    let cpsr = Cpsr(0x4000_0010);
+    assert!(cpsr.is_aarch32());
+    assert_eq!(cpsr.aarch32_mode(), Some(0x10));
+    assert_eq!(cpsr.exception_level(), None);
+    assert_eq!(cpsr.sp_elx(), None);
+    assert_eq!(cpsr.to_string(), "nZcv AArch32 mode 0x10 daif");
+}
+
+#[test]
+fn masks_and_features() {
+    let cpsr = Cpsr(0x3c0 | 1 << 12 | 1 << 22 | 1 << 23 | 1 << 25 | 0b10 << 10);
+    assert!(cpsr.d() && cpsr.a() && cpsr.i() && cpsr.f());
+    assert!(cpsr.ssbs() && cpsr.pan() && cpsr.uao() && cpsr.tco());
+    assert_eq!(cpsr.btype(), 2);
+    assert_eq!(cpsr.to_string(), "nzcv EL0t DAIF SSBS PAN UAO TCO BTYPE=2");
+
+    let cpsr = Cpsr(0x80 | 1 << 12);
+    assert!(!cpsr.d() && !cpsr.a() && cpsr.i() && !cpsr.f());
+    assert!(!cpsr.pan() && !cpsr.uao() && !cpsr.tco());
+    assert_eq!(cpsr.btype(), 0);
+    assert_eq!(cpsr.to_string(), "nzcv EL0t daIf SSBS");
+}
+
+#[test]
+fn decoded_from_thread_state() {
+    let mut state = Vec::new();
+    for _ in 0..33 {
+        state.extend_from_slice(&0u64.to_le_bytes());
+    }
+    state.extend_from_slice(&0x6000_1000u32.to_le_bytes());
+    state.extend_from_slice(&0u32.to_le_bytes());
+    let command = thread(&[(6, state)]);
+    let buf = core(1, command.len() as u32, &command);
+    let macho = Macho::from_stream(&buf[..]).unwrap();
+    let threads = macho.get_threads();
+    let context = threads[0].context.unwrap();
+    assert_eq!(context.register("cpsr"), Some(0x6000_1000));
+    match context {
+        ThreadState::Arm64(state) => {
+            assert_eq!(state.cpsr, Cpsr(0x6000_1000));
+            assert_eq!(state.cpsr.to_string(), "nZCv EL0t daif SSBS");
+        }
+        state => panic!("unexpected {:?}", state),
+    }
+}
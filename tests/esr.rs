@@ -0,0 +1,151 @@
+use mach_dump::esr::{Esr, ExceptionClass, FaultStatus, PacKey};
+use mach_dump::thread_state::ArmExceptionState64;
+
+#[test]
+fn data_abort() {
+    // Lower EL, 32-bit instruction, write, translation fault at level 3
+    let esr = Esr(0x9200_0047);
+    assert_eq!(esr.class(), ExceptionClass::DataAbort { lower_el: true });
+    assert!(esr.il());
+    assert_eq!(esr.iss(), 0x47);
+    assert_eq!(
+        esr.fault_status(),
+        Some(FaultStatus::Translation { level: 3 })
+    );
+    assert!(esr.fault_status().unwrap().is_translation());
+    assert_eq!(esr.is_write(), Some(true));
+    assert_eq!(esr.far_valid(), Some(true));
+    assert_eq!(esr.brk_immediate(), None);
+    assert_eq!(esr.pac_key(), None);
+    assert_eq!(
+        esr.to_string(),
+        "data abort: translation fault (level 3) on write"
+    );
+
+    // Same EL, read, permission fault at level 3
+    let esr = Esr(0x9600_000f);
+    assert_eq!(esr.class(), ExceptionClass::DataAbort { lower_el: false });
+    assert!(esr.fault_status().unwrap().is_permission());
+    assert_eq!(esr.is_write(), Some(false));
+    assert_eq!(
+        esr.to_string(),
+        "data abort: permission fault (level 3) on read"
+    );
+
+    // FnV set, the fault address register is not valid
+    assert_eq!(Esr(0x9200_0407).far_valid(), Some(false));
+    assert_eq!(Esr(0x9200_0011).fault_status(), Some(FaultStatus::TagCheck));
+}
+
+#[test]
+fn instruction_abort() {
+    let esr = Esr(0x8200_0006);
+    assert_eq!(
+        esr.class(),
+        ExceptionClass::InstructionAbort { lower_el: true }
+    );
+    assert_eq!(
+        esr.fault_status(),
+        Some(FaultStatus::Translation { level: 2 })
+    );
+    assert_eq!(esr.is_write(), None);
+    assert_eq!(
+        esr.to_string(),
+        "instruction abort: translation fault (level 2)"
+    );
+}
+
+#[test]
+fn fault_status_codes() {
+    assert_eq!(
+        FaultStatus::from(0x01),
+        FaultStatus::AddressSize { level: 1 }
+    );
+    assert_eq!(
+        FaultStatus::from(0x0a),
+        FaultStatus::AccessFlag { level: 2 }
+    );
+    assert_eq!(
+        FaultStatus::from(0x0c),
+        FaultStatus::Permission { level: 0 }
+    );
+    assert_eq!(
+        FaultStatus::from(0x15),
+        FaultStatus::SyncExternalWalk { level: 1 }
+    );
+    assert_eq!(FaultStatus::from(0x21), FaultStatus::Alignment);
+    assert_eq!(FaultStatus::from(0x3f), FaultStatus::Other(0x3f));
+    assert!(!FaultStatus::from(0x21).is_translation());
+    assert!(!FaultStatus::from(0x21).is_permission());
+}
+
+#[test]
+fn other_classes() {
+    // Not aborts, so no fault status or access direction
+    let esr = Esr(0x5600_0080);
+    assert_eq!(esr.class(), ExceptionClass::Svc);
+    assert_eq!(esr.fault_status(), None);
+    assert_eq!(esr.is_write(), None);
+    assert_eq!(esr.far_valid(), None);
+    assert_eq!(esr.to_string(), "supervisor call");
+
+    assert_eq!(Esr(0).class(), ExceptionClass::Unknown);
+    assert!(!Esr(0).il());
+    assert_eq!(
+        Esr(0xd000_0000).class(),
+        ExceptionClass::Watchpoint { lower_el: true }
+    );
+    let esr = Esr(0xfc00_0000);
+    assert_eq!(esr.class(), ExceptionClass::Other(0x3f));
+    assert_eq!(esr.to_string(), "exception class 0x3f");
+}
+
+#[test]
+fn brk() {
+    let esr = Esr(0xf200_0001);
+    assert_eq!(esr.class(), ExceptionClass::Brk);
+    assert_eq!(esr.brk_immediate(), Some(1));
+    assert_eq!(esr.pac_key(), None);
+    assert_eq!(esr.to_string(), "brk #0x1");
+}
+
+#[test]
+fn pointer_authentication_failure() {
+    // brk emitted by the compiler for a failed autib
+    let esr = Esr(0xf200_c471);
+    assert_eq!(esr.pac_key(), Some(PacKey::IB));
+    assert_eq!(
+        esr.to_string(),
+        "brk #0xc471 (pointer authentication failure with key IB)"
+    );
+    assert_eq!(Esr(0xf200_c474).pac_key(), None);
+
+    // FPAC for a failed autda
+    let esr = Esr(0x7200_0002);
+    assert_eq!(esr.class(), ExceptionClass::PacFailure);
+    assert_eq!(esr.pac_key(), Some(PacKey::DA));
+    assert_eq!(
+        esr.to_string(),
+        "pointer authentication failure with key DA"
+    );
+}
+
+#[test]
+fn fault_address() {
+    let mut state = ArmExceptionState64 {
+        far: 0x10,
+        esr: Esr(0x9200_0006),
+        exception: 0,
+    };
+    assert_eq!(state.fault_address(), Some(0x10));
+    assert_eq!(
+        state.description(),
+        "data abort: translation fault (level 2) on read at 0x10"
+    );
+
+    state.esr = Esr(0x9200_0406);
+    assert_eq!(state.fault_address(), None);
+    state.esr = Esr(0xf200_0001);
+    assert_eq!(state.fault_address(), None);
+    assert_eq!(state.description(), "brk #0x1");
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::{core, Writer};
+use mach_dump::cpu::{
+    CpuSubType, CpuType, CPU_SUBTYPE_ARM64E, CPU_SUBTYPE_ARM64_ALL, CPU_SUBTYPE_X86_ALL,
+    CPU_TYPE_ARM, CPU_TYPE_ARM64, CPU_TYPE_X86_64, MH_MAGIC_64,
+};
+use mach_dump::error::MachoError;
+use mach_dump::fat::{FatFile, FAT_MAGIC, FAT_MAGIC_64};
+use mach_dump::macho_ref::MachoRef;
+
+/// Mach-O header for `cputype` and `cpu_subtype` without commands
+fn slice(cputype: CpuType, cpu_subtype: CpuSubType) -> Vec<u8> {
+    let mut w = Writer::new(false);
+    w.u32(MH_MAGIC_64).u32(cputype.0).u32(cpu_subtype.0);
+    w.u32(2).u32(0).u32(0).u32(0).u32(0);
+    w.buf
+}
+
+/// Universal binary with an x86_64 slice at 0x1000 and an arm64e
+/// slice at 0x2000
+fn fat() -> Vec<u8> {
+    let mut w = Writer::new(true);
+    w.u32(FAT_MAGIC).u32(2);
+    w.u32(CPU_TYPE_X86_64.0)
+        .u32(CPU_SUBTYPE_X86_ALL.0)
+        .u32(0x1000)
+        .u32(32)
+        .u32(12);
+    w.u32(CPU_TYPE_ARM64.0)
+        .u32(CPU_SUBTYPE_ARM64E.0)
+        .u32(0x2000)
+        .u32(32)
+        .u32(14);
+    w.pad(0x1000);
+    w.bytes(&slice(CPU_TYPE_X86_64, CPU_SUBTYPE_X86_ALL));
+    w.pad(0x2000);
+    w.bytes(&slice(CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64E));
+    w.buf
+}
+
+#[test]
+fn lists_slices() {
+    let buf = fat();
+    let fat = FatFile::parse(&buf).unwrap();
+    assert_eq!(fat.magic, FAT_MAGIC);
+    assert_eq!(fat.arches.len(), 2);
+    assert_eq!(fat.arches[1].cputype, CPU_TYPE_ARM64);
+    assert_eq!(fat.arches[1].offset, 0x2000);
+    assert_eq!(fat.arches[1].size, 32);
+    assert_eq!(fat.arches[1].align, 14);
+
+    let macho = fat.macho(&fat.arches[0]).unwrap();
+    assert_eq!(macho.header.cputype, CPU_TYPE_X86_64);
+}
+
+#[test]
+fn finds_slice_by_cpu_type() {
+    let buf = fat();
+    let fat = FatFile::parse(&buf).unwrap();
+
+    let arch = fat.find(CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64E).unwrap();
+    assert_eq!(arch.offset, 0x2000);
+    // Capability bits are ignored
+    let arch = fat
+        .find(
+            CPU_TYPE_ARM64,
+            CpuSubType(CPU_SUBTYPE_ARM64E.0 | 0x8000_0000),
+        )
+        .unwrap();
+    assert_eq!(arch.offset, 0x2000);
+    // Other subtypes fall back to the same CPU type
+    let arch = fat.find(CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64_ALL).unwrap();
+    assert_eq!(arch.offset, 0x2000);
+    assert!(fat.find(CPU_TYPE_ARM, CpuSubType(0)).is_none());
+
+    // Slice matching a core dump
+    let core = core(0, 0, &[]);
+    let header = MachoRef::parse(&core).unwrap().header;
+    let arch = fat.find_for(&header).unwrap();
+    assert_eq!(arch.cputype, CPU_TYPE_ARM64);
+}
+
+#[test]
+fn slice_out_of_bounds() {
+    let mut buf = fat();
+    // Move the arm64e slice past the end of the file
+    buf[8 + 20 + 8..8 + 20 + 12].copy_from_slice(&0x3000u32.to_be_bytes());
+    let fat = FatFile::parse(&buf).unwrap();
+    assert!(fat.slice(&fat.arches[0]).is_ok());
+    assert!(matches!(
+        fat.slice(&fat.arches[1]),
+        Err(MachoError::SliceOutOfBounds {
+            offset: 28,
+            slice_offset: 0x3000,
+            size: 32
+        })
+    ));
+    assert!(fat.macho(&fat.arches[1]).is_err());
+}
+
+#[test]
+fn slice_offset_overflows() {
+    let mut w = Writer::new(true);
+    w.u32(FAT_MAGIC_64).u32(1);
+    w.u32(CPU_TYPE_ARM64.0)
+        .u32(0)
+        .u64(u64::MAX)
+        .u64(32)
+        .u32(14)
+        .u32(0);
+    let buf = w.buf;
+    let fat = FatFile::parse(&buf).unwrap();
+    assert!(matches!(
+        fat.slice(&fat.arches[0]),
+        Err(MachoError::SliceOutOfBounds { offset: 8, .. })
+    ));
+}
+
+#[test]
+fn truncated_arch_table() {
+    let buf = fat();
+    assert!(matches!(
+        FatFile::parse(&buf[..8 + 20 + 4]),
+        Err(MachoError::TruncatedHeader { .. })
+    ));
+}
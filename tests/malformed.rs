@@ -0,0 +1,140 @@
+//! Malformed core dumps must be rejected or recovered from, never
+//! crash the process.
+
+mod common;
+
+use common::{core, core_with_segments, segment, thread};
+use mach_dump::cpu::CPU_TYPE_ARM64;
+use mach_dump::endian::Endian;
+use mach_dump::error::MachoError;
+use mach_dump::load_command::{CommandType, ThreadCommand};
+use mach_dump::macho::Macho;
+use mach_dump::thread_state::ArmDebugState64;
+use mach_dump::validate::{DiagnosticKind, ValidationMode};
+use std::io::Cursor;
+
+#[test]
+fn huge_ncmds() {
+    let buf = core(0xffff_ffff, 0, &[]);
+    for mode in [ValidationMode::Strict, ValidationMode::Lenient] {
+        let _ = Macho::from_reader_with(Cursor::new(buf.clone()), mode);
+        let _ = Macho::from_stream_with(&buf[..], mode);
+    }
+}
+
+#[test]
+fn stream_empty_segment_in_gap() {
+    let buf = core_with_segments(
+        &[
+            segment(0x10000, 0x1000, 0x10),
+            segment(0x20000, 0x2000, 0x10),
+            segment(0x30000, 0x1800, 0),
+        ],
+        0x3000,
+    );
+    let macho = Macho::from_stream_with(&buf[..], ValidationMode::Lenient).unwrap();
+    for segment in &macho.segments {
+        let content = segment.content().unwrap();
+        assert_eq!(content.len(), segment.filesize);
+    }
+}
+
+#[test]
+fn stream_ends_in_gap() {
+    let buf = core_with_segments(
+        &[
+            segment(0x10000, 0x1000, 0x10),
+            segment(0x20000, 0x3000, 0x10),
+        ],
+        0x1800,
+    );
+    let macho = Macho::from_stream_with(&buf[..], ValidationMode::Lenient).unwrap();
+    for segment in &macho.segments {
+        let _ = segment.content();
+    }
+}
+
+#[test]
+fn thread_command_smaller_than_header() {
+    let mut buf = 4u32.to_le_bytes().to_vec();
+    buf.extend_from_slice(&4u32.to_le_bytes());
+    assert!(matches!(
+        ThreadCommand::new(&buf, 0, Endian::Little, CPU_TYPE_ARM64),
+        Err(MachoError::LoadCommandOutOfBounds { cmdsize: 4, .. })
+    ));
+}
+
+#[test]
+fn thread_without_general_state() {
+    // LC_THREAD with one ARM_THREAD_STATE64 flavor of 4 words,
+    // too short for the registers
+    let mut thread = Vec::new();
+    for field in [4u32, 32, 6, 4, 0, 0, 0, 0] {
+        thread.extend_from_slice(&field.to_le_bytes());
+    }
+    let buf = core(1, thread.len() as u32, &thread);
+    let macho = Macho::from_stream_with(&buf[..], ValidationMode::Lenient).unwrap();
+    let threads = macho.get_threads();
+    assert_eq!(threads.len(), 1);
+    assert!(threads[0].context.is_none());
+    // Threads borrow the parsed command instead of copying it
+    assert!(matches!(
+        &macho.load_commands[0],
+        CommandType::ThreadCommand(tc) if std::ptr::eq(&**tc, threads[0].command)
+    ));
+    assert_eq!(macho.diagnostics.len(), 1);
+    assert_eq!(macho.diagnostics[0].offset, 0x28);
+    assert_eq!(
+        macho.diagnostics[0].kind,
+        DiagnosticKind::ThreadStateTruncated {
+            flavor: 6,
+            expected: 68,
+            count: 4
+        }
+    );
+    assert!(Macho::from_stream(&buf[..]).is_err());
+}
+
+#[test]
+fn watchpoint_near_end_of_address_space() {
+    let mut debug = ArmDebugState64 {
+        bvr: [0; 16],
+        bcr: [0; 16],
+        wvr: [0; 16],
+        wcr: [0; 16],
+        mdscr_el1: 0,
+    };
+    debug.wvr[0] = u64::MAX;
+    // Enabled, write, BAS selecting the last byte
+    debug.wcr[0] = 1 | 0b10 << 3 | 0x80 << 5;
+    // Enabled, read, MASK of 8 bits
+    debug.wvr[1] = u64::MAX;
+    debug.wcr[1] = 1 | 0b01 << 3 | 8 << 24;
+    let watchpoints = debug.watchpoints();
+    assert_eq!(watchpoints[0].address, u64::MAX);
+    assert_eq!(watchpoints[0].size, 1);
+    assert_eq!(watchpoints[1].address, 0xffff_ffff_ffff_ff00);
+    assert_eq!(watchpoints[1].size, 0x100);
+    assert!(watchpoints[1].read && !watchpoints[1].write);
+}
+
+#[test]
+fn unsupported_thread_flavor() {
+    let command = thread(&[(99, vec![0; 8])]);
+    let buf = core(1, command.len() as u32, &command);
+    assert!(matches!(
+        Macho::from_stream(&buf[..]),
+        Err(MachoError::UnsupportedThreadFlavor {
+            offset: 0x28,
+            flavor: 99
+        })
+    ));
+
+    let macho = Macho::from_stream_with(&buf[..], ValidationMode::Lenient).unwrap();
+    assert_eq!(macho.diagnostics.len(), 1);
+    assert_eq!(macho.diagnostics[0].offset, 0x28);
+    assert_eq!(
+        macho.diagnostics[0].kind,
+        DiagnosticKind::UnsupportedThreadFlavor { flavor: 99 }
+    );
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::{core_with_segments, note};
+use mach_dump::macho::Macho;
+use mach_dump::note::{NoteData, NoteRegistry, THREAD_EXTRABITS};
+
+#[test]
+fn thread_extrabits() {
+    let mut payload = Vec::new();
+    for field in [1u32, 2, 2] {
+        payload.extend_from_slice(&field.to_le_bytes());
+    }
+    for word in [0x1234u64, 7, 0x5678, 8] {
+        payload.extend_from_slice(&word.to_le_bytes());
+    }
+    let mut buf = core_with_segments(
+        &[note(THREAD_EXTRABITS, 0x100, payload.len() as u64)],
+        0x100,
+    );
+    buf.extend_from_slice(&payload);
+
+    let macho = Macho::from_stream(&buf[..]).unwrap();
+    match macho.notes[0].decode(&NoteRegistry::default()).unwrap() {
+        NoteData::ThreadExtrabits(extrabits) => {
+            assert_eq!(extrabits.thread_count(), 2);
+            assert_eq!(extrabits.thread(1), Some(&[0x5678, 8][..]));
+            assert_eq!(extrabits.thread_id(0), Some(0x1234));
+            assert_eq!(extrabits.thread_id(2), None);
+        }
+        data => panic!("unexpected {:?}", data),
+    }
+
+    // Thread count past the end of the payload
+    buf[0x100 + 8] = 3;
+    let macho = Macho::from_stream(&buf[..]).unwrap();
+    assert!(macho.notes[0].decode(&NoteRegistry::default()).is_err());
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::{core_with_segments, segment};
+use mach_dump::macho::Macho;
+use mach_dump::region::RegionKind;
+
+fn macho() -> Macho {
+    let buf = core_with_segments(
+        &[
+            segment(0x10000, 0x1000, 0x1000),
+            segment(0x11000, 0x2000, 0x1000),
+            segment(0x20000, 0x3000, 0x1000),
+        ],
+        0x4000,
+    );
+    Macho::from_stream(&buf[..]).unwrap()
+}
+
+fn kinds(
+    macho: &Macho,
+    labels: &[(std::ops::Range<u64>, RegionKind)],
+) -> Vec<(u64, u64, RegionKind)> {
+    macho
+        .region_map_with(labels)
+        .regions
+        .into_iter()
+        .map(|region| (region.start, region.end, region.kind))
+        .collect()
+}
+
+#[test]
+fn heap_is_not_detected() {
+    let macho = macho();
+    let map = macho.region_map();
+    assert!(map
+        .regions
+        .iter()
+        .all(|region| region.kind != RegionKind::Heap));
+    assert_eq!(
+        kinds(&macho, &[]),
+        [
+            (0x10000, 0x12000, RegionKind::Unknown),
+            (0x12000, 0x20000, RegionKind::Gap),
+            (0x20000, 0x21000, RegionKind::Unknown),
+        ]
+    );
+}
+
+#[test]
+fn heap_from_labels() {
+    let macho = macho();
+    let labels = [(0x11000..0x12000, RegionKind::Heap)];
+    assert_eq!(
+        kinds(&macho, &labels),
+        [
+            (0x10000, 0x11000, RegionKind::Unknown),
+            (0x11000, 0x12000, RegionKind::Heap),
+            (0x12000, 0x20000, RegionKind::Gap),
+            (0x20000, 0x21000, RegionKind::Unknown),
+        ]
+    );
+    let map = macho.region_map_with(&labels).to_string();
+    assert!(
+        map.lines()
+            .any(|line| line.starts_with("heap")
+                && line.contains("0000000000011000-0000000000012000"))
+    );
+}
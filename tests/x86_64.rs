@@ -0,0 +1,117 @@
+mod common;
+
+use common::{thread, Writer};
+use mach_dump::cpu::{CPU_TYPE_X86_64, MH_MAGIC_64};
+use mach_dump::macho::Macho;
+use mach_dump::thread::RegisterContext;
+use mach_dump::thread_state::{
+    FlavorState, X86_AVX_STATE64, X86_EXCEPTION_STATE64, X86_FLOAT_STATE64, X86_THREAD_STATE64,
+};
+
+const XMM15: u128 = 0x0f0e_0d0c_0b0a_0908_0706_0504_0302_0100;
+const YMMH3: u128 = 0xffee_ddcc_bbaa_9988_7766_5544_3322_1100;
+
+/// `x86_float_state64` with a few distinct registers set
+fn float_state(w: &mut Writer) {
+    w.bytes(&[0; 8]);
+    w.u16(0x037f).u16(0x0020).bytes(&[0xff, 0]).u16(0x0123);
+    w.u32(0x4000).u16(0x2b).u16(0);
+    w.u32(0x8000).u16(0x23).u16(0);
+    w.u32(0x1f80).u32(0xffff);
+    for n in 0..8u8 {
+        w.bytes(&[n; 10]).bytes(&[0; 6]);
+    }
+    for n in 0..16 {
+        w.bytes(&(if n == 15 { XMM15 } else { 0 }).to_le_bytes());
+    }
+    w.bytes(&[0; 96 + 4]);
+}
+
+fn core() -> Macho {
+    let mut general = Writer::new(false);
+    for n in 0..21u64 {
+        general.u64(n);
+    }
+    let mut float = Writer::new(false);
+    float_state(&mut float);
+    let mut avx = Writer::new(false);
+    float_state(&mut avx);
+    avx.bytes(&[0; 64]);
+    for n in 0..16 {
+        avx.bytes(&(if n == 3 { YMMH3 } else { 0 }).to_le_bytes());
+    }
+    let mut exception = Writer::new(false);
+    exception.u16(14).u16(2).u32(6).u64(0xdead_0000);
+
+    let command = thread(&[
+        (X86_THREAD_STATE64, general.buf),
+        (X86_FLOAT_STATE64, float.buf),
+        (X86_AVX_STATE64, avx.buf),
+        (X86_EXCEPTION_STATE64, exception.buf),
+    ]);
+    let mut w = Writer::new(false);
+    w.u32(MH_MAGIC_64).u32(CPU_TYPE_X86_64.0).u32(3).u32(4);
+    w.u32(1).u32(command.len() as u32).u32(0).u32(0);
+    w.bytes(&command);
+    Macho::from_stream(&w.buf[..]).unwrap()
+}
+
+#[test]
+fn general_state() {
+    let macho = core();
+    let threads = macho.get_threads();
+    let context = threads[0].context.unwrap();
+    assert_eq!(context.register("rax"), Some(0));
+    assert_eq!(context.sp(), 7);
+    assert_eq!(context.fp(), 6);
+    assert_eq!(context.pc(), 16);
+    assert_eq!(context.register("gs"), Some(20));
+}
+
+#[test]
+fn float_avx_and_exception_states() {
+    let macho = core();
+    assert!(macho.diagnostics.is_empty());
+    let threads = macho.get_threads();
+    let flavors = &threads[0].command.flavors;
+    assert_eq!(flavors.len(), 4);
+
+    match &flavors[1].state {
+        FlavorState::X86Float64(float) => {
+            assert_eq!(float.fcw, 0x037f);
+            assert_eq!(float.fsw, 0x0020);
+            assert_eq!(float.ftw, 0xff);
+            assert_eq!(float.fop, 0x0123);
+            assert_eq!(float.ip, 0x4000);
+            assert_eq!(float.cs, 0x2b);
+            assert_eq!(float.dp, 0x8000);
+            assert_eq!(float.ds, 0x23);
+            assert_eq!(float.mxcsr, 0x1f80);
+            assert_eq!(float.mxcsrmask, 0xffff);
+            assert_eq!(float.stmm[7], [7; 10]);
+            assert_eq!(float.xmm[15], XMM15);
+            assert_eq!(float.xmm[14], 0);
+        }
+        state => panic!("unexpected {:?}", state),
+    }
+
+    match &flavors[2].state {
+        FlavorState::X86Avx64(avx) => {
+            assert_eq!(avx.float.mxcsr, 0x1f80);
+            assert_eq!(avx.float.xmm[15], XMM15);
+            assert_eq!(avx.ymm(3), (0, YMMH3));
+            assert_eq!(avx.ymm(15), (XMM15, 0));
+        }
+        state => panic!("unexpected {:?}", state),
+    }
+
+    match &flavors[3].state {
+        FlavorState::X86Exception64(exception) => {
+            assert_eq!(exception.trapno, 14);
+            assert_eq!(exception.cpu, 2);
+            assert_eq!(exception.err, 6);
+            assert_eq!(exception.faultvaddr, 0xdead_0000);
+        }
+        state => panic!("unexpected {:?}", state),
+    }
+}